@@ -0,0 +1,56 @@
+//! Stand-in external controller that runs a fixed-time signal over the controller socket.
+//!
+//! Enable the server in `config.json` with `"controller": { "enabled": true, "lock_step": true }`,
+//! start the simulation and then run `cargo run --example controller [address]`.
+
+use smart_road::car::Direction;
+use smart_road::controller::{Command, Commands, WorldState};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+
+/// Seconds each axis gets to drive before the other one
+const GREEN_TIME: f32 = 8.0;
+
+fn main() -> std::io::Result<()> {
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("127.0.0.1:7878"));
+    let mut stream = TcpStream::connect(&address)?;
+    let reader = BufReader::new(stream.try_clone()?);
+
+    for line in reader.lines() {
+        let state: WorldState = match serde_json::from_str(&line?) {
+            Ok(state) => state,
+            Err(err) => {
+                eprintln!("Unreadable world state: {err}");
+                continue;
+            }
+        };
+
+        let north_south = state.time % (2.0 * GREEN_TIME) < GREEN_TIME;
+        let commands = Commands {
+            commands: state
+                .cars
+                .iter()
+                .map(|car| Command {
+                    tick: state.tick,
+                    car_id: car.id,
+                    speed: None,
+                    permission: if car.leaving_intersection {
+                        None
+                    } else {
+                        Some(
+                            matches!(car.direction_from, Direction::North | Direction::South)
+                                == north_south,
+                        )
+                    },
+                })
+                .collect(),
+        };
+
+        let mut answer = serde_json::to_string(&commands)?;
+        answer.push('\n');
+        stream.write_all(answer.as_bytes())?;
+    }
+    Ok(())
+}
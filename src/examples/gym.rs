@@ -0,0 +1,38 @@
+//! Runs a headless episode with a fixed-time policy through the gym interface and prints the
+//! rewards: `cargo run --example gym [seed]`.
+
+use smart_road::config::Config;
+use smart_road::gym::{Action, Environment};
+
+/// Steps each axis gets to drive before the other one
+const GREEN_STEPS: u32 = 16;
+
+fn main() {
+    let seed = std::env::args()
+        .nth(1)
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or(0);
+    let mut environment = Environment::new(Config::load());
+    environment.reset(seed);
+
+    let mut total = 0.0;
+    let mut step = 0;
+    loop {
+        let north_south = step % (2 * GREEN_STEPS) < GREEN_STEPS;
+        let action = Action::Approaches([north_south, !north_south, north_south, !north_south]);
+        let (_, reward, done) = environment.step(&action);
+
+        total += reward;
+        step += 1;
+        if done {
+            break;
+        }
+    }
+
+    let statistics = environment.get_global_state().get_statistics();
+    println!(
+        "seed {seed}: {step} steps, reward {total:.2}, {} vehicles, {} close calls",
+        statistics.get_max_vehicles(),
+        statistics.get_close_calls()
+    );
+}
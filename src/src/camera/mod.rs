@@ -0,0 +1,119 @@
+use macroquad::{
+    camera::Camera2D,
+    input::{is_mouse_button_down, mouse_position, mouse_wheel, MouseButton},
+    prelude::Vec2,
+    window,
+};
+
+use crate::constants::{MAX_ZOOM, MIN_ZOOM, ZOOM_STEP};
+use crate::intersection::dimensions::Dimensions;
+
+/// View of the intersection. The simulation lives in world coordinates in metres, the camera
+/// decides which part of the world is shown in the window and how big.
+#[derive(Debug, Clone, PartialEq, Copy)]
+pub struct Camera {
+    /// Size of the world in metres
+    world: Vec2,
+    /// Point of the world shown in the middle of the window
+    center: Vec2,
+    /// Magnification, the whole world fits the window at 1
+    zoom: f32,
+    /// Keeps the selected car in the middle of the window
+    following: bool,
+    /// Mouse position the current drag continues from
+    drag_from: Option<Vec2>,
+}
+
+impl Camera {
+    /// Camera fitting the whole world into the window
+    pub fn new(dimensions: Dimensions) -> Self {
+        let center = dimensions.get_center();
+
+        Camera {
+            world: Vec2::new(2.0 * dimensions.get_x_max(), 2.0 * dimensions.get_y_max()),
+            center: Vec2::new(center.x, center.y),
+            zoom: 1.0,
+            following: false,
+            drag_from: None,
+        }
+    }
+
+    /// Zooms around the mouse with the wheel and pans while the right or middle mouse button is
+    /// dragged. Panning stops following the selected car.
+    pub fn handle_mouse(&mut self) {
+        let mouse = Vec2::from(mouse_position());
+
+        let (_, wheel) = mouse_wheel();
+        if wheel != 0.0 {
+            let anchor = self.screen_to_world(mouse);
+            self.zoom = (self.zoom * ZOOM_STEP.powf(wheel.signum())).clamp(MIN_ZOOM, MAX_ZOOM);
+            self.center = anchor - (mouse - Self::get_screen_center()) / self.get_scale();
+        }
+
+        if is_mouse_button_down(MouseButton::Right) || is_mouse_button_down(MouseButton::Middle) {
+            if let Some(from) = self.drag_from {
+                if from != mouse {
+                    self.center -= (mouse - from) / self.get_scale();
+                    self.following = false;
+                }
+            }
+            self.drag_from = Some(mouse);
+        } else {
+            self.drag_from = None;
+        }
+    }
+
+    /// Moves the camera along with the followed car, or stops following once it is gone
+    pub fn follow(&mut self, target: Option<Vec2>) {
+        if !self.following {
+            return;
+        }
+        match target {
+            Some(target) => self.center = target,
+            None => self.following = false,
+        }
+    }
+
+    pub fn screen_to_world(&self, point: Vec2) -> Vec2 {
+        self.center + (point - Self::get_screen_center()) / self.get_scale()
+    }
+
+    /// Pixels per metre. The window size is read every frame, so resizing it only changes the
+    /// view and never the simulation.
+    pub fn get_scale(&self) -> f32 {
+        let fit =
+            (window::screen_width() / self.world.x).min(window::screen_height() / self.world.y);
+        fit * self.zoom
+    }
+
+    /// Camera to draw the simulation with, `set_default_camera` goes back to drawing on the
+    /// window for the overlays
+    pub fn get_camera_2d(&self) -> Camera2D {
+        Camera2D {
+            target: self.center,
+            zoom: Vec2::new(
+                2.0 * self.get_scale() / window::screen_width(),
+                -2.0 * self.get_scale() / window::screen_height(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    fn get_screen_center() -> Vec2 {
+        Vec2::new(window::screen_width() / 2.0, window::screen_height() / 2.0)
+    }
+
+    //setters
+    pub fn set_following(&mut self, new_value: bool) {
+        self.following = new_value;
+    }
+
+    //getters
+    pub fn get_zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.following
+    }
+}
@@ -1,8 +1,14 @@
-use macroquad::{prelude::GREEN, shapes::draw_circle};
+use macroquad::{
+    prelude::{Rect, GREEN, YELLOW},
+    shapes::{draw_circle, draw_rectangle_lines},
+};
 
+use crate::constants::CLOSE_CALL_MARGIN;
 use crate::state::GlobalState;
+use crate::statistics::trips::Trip;
+use crate::v2i::communicate;
 
-use super::{Car, Direction};
+use super::{vehicles::VehicleType, Car, Direction};
 
 // #[derive(Debug, Clone, PartialEq)]
 pub type Cars = Vec<Car>;
@@ -13,6 +19,8 @@ pub trait CarTraits {
     fn remove_finished_cars(&mut self, global_state: &mut GlobalState);
     fn move_cars(&mut self, global_state: &mut GlobalState);
     fn draw_cars(&self, global_state: &GlobalState);
+    fn draw_stop_reasons(&self, global_state: &GlobalState);
+    fn draw_detection_zones(&self, global_state: &GlobalState);
 }
 
 impl CarTraits for Vec<Car> {
@@ -25,11 +33,6 @@ impl CarTraits for Vec<Car> {
     }
 
     fn remove_finished_cars(&mut self, global_state: &mut GlobalState) {
-        let car_width = global_state
-            .get_intersection()
-            .get_dimensions()
-            .get_car_width();
-
         let x_max = global_state.get_intersection().get_dimensions().get_x_max();
         let y_max = global_state.get_intersection().get_dimensions().get_y_max();
         let center = global_state
@@ -40,30 +43,32 @@ impl CarTraits for Vec<Car> {
         let mut new_cars = Cars::new();
 
         for car in self {
+            let length = car.get_vehicle_type().get_length();
+
             match car.get_current_direction() {
                 Direction::North => {
-                    if car.get_y() > center.y - y_max - car_width {
+                    if car.get_y() > center.y - y_max - length {
                         new_cars.push(car.clone());
                         continue;
                     }
                     compare_car_statistics(car, global_state)
                 }
                 Direction::South => {
-                    if car.get_y() < y_max + center.y + car_width {
+                    if car.get_y() < y_max + center.y + length {
                         new_cars.push(car.clone());
                         continue;
                     }
                     compare_car_statistics(car, global_state)
                 }
                 Direction::West => {
-                    if car.get_x() > center.x - x_max - car_width {
+                    if car.get_x() > center.x - x_max - length {
                         new_cars.push(car.clone());
                         continue;
                     }
                     compare_car_statistics(car, global_state)
                 }
                 Direction::East => {
-                    if car.get_x() < x_max + center.x + car_width {
+                    if car.get_x() < x_max + center.x + length {
                         new_cars.push(car.clone());
                         continue;
                     }
@@ -76,45 +81,47 @@ impl CarTraits for Vec<Car> {
     }
 
     fn move_cars(&mut self, global_state: &mut GlobalState) {
-        for car in &mut *self {
-            let window_width = global_state.get_intersection().get_window_width();
-            let window_height = global_state.get_intersection().get_window_height();
+        global_state.push_car_history();
+        global_state.update_perceptions();
+
+        let center = global_state
+            .get_intersection()
+            .get_dimensions()
+            .get_center();
 
+        for car in &mut *self {
             if !car.is_leaving_intersection() {
                 match car.get_current_direction() {
                     Direction::South => {
-                        if car.get_y() >= window_height / 2.0 + car.get_turn_offset() {
+                        if car.get_y() >= center.y + car.get_turn_offset() {
                             car.change_direction();
                             car.leaving_intersection(true);
                             car.calculate_rotation();
-                            add_statistics_car(global_state)
+                            add_statistics_car(car, global_state)
                         }
                     }
                     Direction::North => {
-                        if car.get_y() <= window_height / 2.0 + car.get_turn_offset() {
+                        if car.get_y() <= center.y + car.get_turn_offset() {
                             car.change_direction();
                             car.leaving_intersection(true);
                             car.calculate_rotation();
-                            add_statistics_car(global_state)
-
+                            add_statistics_car(car, global_state)
                         }
                     }
                     Direction::East => {
-                        if car.get_x() >= window_width / 2.0 + car.get_turn_offset() {
+                        if car.get_x() >= center.x + car.get_turn_offset() {
                             car.change_direction();
                             car.leaving_intersection(true);
                             car.calculate_rotation();
-                            add_statistics_car(global_state)
-
+                            add_statistics_car(car, global_state)
                         }
                     }
                     Direction::West => {
-                        if car.get_x() <= window_width / 2.0 + car.get_turn_offset() {
+                        if car.get_x() <= center.x + car.get_turn_offset() {
                             car.change_direction();
                             car.leaving_intersection(true);
                             car.calculate_rotation();
-                            add_statistics_car(global_state)
-
+                            add_statistics_car(car, global_state)
                         }
                     }
                     _ => unreachable!(),
@@ -123,62 +130,176 @@ impl CarTraits for Vec<Car> {
 
             car.set_driving_time(car.get_driving_time() + 1.0 / 60.0);
             car.adjust_speed(&global_state);
+            communicate(car, global_state);
             get_max_min_speed(car, global_state);
         }
 
         global_state.set_cars(self.to_owned());
+        count_close_calls(global_state);
     }
 
     fn draw_cars(&self, global_state: &GlobalState) {
         // Helper for debugging - remove later
-        fn draw_point(x: f32, y: f32) {
-            draw_circle(x, y, 2.0, GREEN)
-        }
+        let dimensions = global_state.get_intersection().get_dimensions();
+        let line_width = dimensions.get_line_width();
+
+        let draw_point = |x: f32, y: f32| draw_circle(x, y, 2.0 * line_width, GREEN);
+
+        let selected = global_state.get_selected_car().map(|car| car.get_id());
 
         for car in self {
             car.draw(&global_state);
-            draw_point(car.get_x(), car.get_y())
+            draw_point(car.get_x(), car.get_y());
+
+            if selected == Some(car.get_id()) {
+                let footprint = car.get_footprint();
+                draw_rectangle_lines(
+                    footprint.x - 2.0 * line_width,
+                    footprint.y - 2.0 * line_width,
+                    footprint.w + 4.0 * line_width,
+                    footprint.h + 4.0 * line_width,
+                    2.0 * line_width,
+                    YELLOW,
+                );
+            }
+        }
+    }
+
+    /// Debug mode: why each car is stopped and an arrow to the vehicle blocking it
+    fn draw_stop_reasons(&self, global_state: &GlobalState) {
+        for car in self {
+            car.draw_stop_reason(self, global_state);
+        }
+    }
+
+    /// Debug mode: the areas each car checks before driving on
+    fn draw_detection_zones(&self, global_state: &GlobalState) {
+        for car in self {
+            car.draw_detection_zones(global_state);
         }
     }
 }
 
-fn add_statistics_car(global_state: &mut GlobalState) {
-    let mut old_stats = global_state.get_statistics();
+fn add_statistics_car(car: &Car, global_state: &mut GlobalState) {
+    let old_stats = global_state.get_statistics_mut();
 
-    old_stats.set_max_vehicles(old_stats.get_max_vehicles() + 1.0);
+    // Cyclists are only counted in their own class
+    if car.get_vehicle_type() != VehicleType::Bicycle {
+        old_stats.set_max_vehicles(old_stats.get_max_vehicles() + 1.0);
+    }
 
-    global_state.set_statistics(old_stats);
+    let mut class_stats = old_stats.get_class_statistics(car.get_vehicle_type());
+    class_stats.vehicles += 1.0;
+    old_stats.set_class_statistics(car.get_vehicle_type(), class_stats);
 }
 
+/// Counts every pair of vehicles that gets within `CLOSE_CALL_MARGIN` of each other once, for
+/// as long as they stay that close.
+fn count_close_calls(global_state: &mut GlobalState) {
+    let car_width = global_state
+        .get_intersection()
+        .get_dimensions()
+        .get_car_width();
+    let margin = CLOSE_CALL_MARGIN * car_width;
+    let previous = global_state.get_close_calls();
+    let cars = global_state.get_cars();
+    let mut close_calls = Vec::new();
+    let mut new_close_calls = Vec::new();
+
+    for (i, car) in cars.iter().enumerate() {
+        let footprint = car.get_footprint();
+        let area = Rect::new(
+            footprint.x - margin,
+            footprint.y - margin,
+            footprint.w + 2.0 * margin,
+            footprint.h + 2.0 * margin,
+        );
+
+        for other in cars.iter().skip(i + 1) {
+            if area.overlaps(&other.get_footprint()) {
+                let pair = (car.get_id(), other.get_id());
+                if !previous.contains(&pair) {
+                    new_close_calls.push((footprint, other.get_footprint()));
+                }
+                close_calls.push(pair);
+            }
+        }
+    }
 
-fn compare_car_statistics(finished_car: &Car, global_state: &mut GlobalState) {
-    let mut statistics = global_state.get_statistics();
+    if !new_close_calls.is_empty() {
+        let dimensions = global_state.get_intersection().get_dimensions();
+        let statistics = global_state.get_statistics_mut();
+        statistics.set_close_calls(statistics.get_close_calls() + new_close_calls.len() as f32);
 
-    if statistics.get_max_time() <= finished_car.get_driving_time() {
+        for (first, second) in new_close_calls {
+            statistics
+                .get_heatmap_mut()
+                .add_close_call(first, second, dimensions);
+        }
+    }
+    global_state.set_close_calls(close_calls);
+}
+
+/// Adds the trip of a vehicle that has left the simulation, at the edge of the world or towed
+/// away, to the statistics
+pub fn compare_car_statistics(finished_car: &Car, global_state: &mut GlobalState) {
+    let statistics = global_state.get_statistics_mut();
+    let is_cyclist = finished_car.get_vehicle_type() == VehicleType::Bicycle;
+
+    if !is_cyclist && statistics.get_max_time() <= finished_car.get_driving_time() {
         statistics.set_max_time(finished_car.get_driving_time())
     }
 
-    if statistics.get_min_time() == 0.0
-        || statistics.get_min_time() >= finished_car.get_driving_time()
+    if !is_cyclist
+        && (statistics.get_min_time() == 0.0
+            || statistics.get_min_time() >= finished_car.get_driving_time())
     {
         statistics.set_min_time(finished_car.get_driving_time())
     }
 
-    global_state.set_statistics(statistics)
+    let mut class_stats = statistics.get_class_statistics(finished_car.get_vehicle_type());
+
+    if class_stats.max_time <= finished_car.get_driving_time() {
+        class_stats.max_time = finished_car.get_driving_time()
+    }
+
+    if class_stats.min_time == 0.0 || class_stats.min_time >= finished_car.get_driving_time() {
+        class_stats.min_time = finished_car.get_driving_time()
+    }
+
+    statistics.set_class_statistics(finished_car.get_vehicle_type(), class_stats);
+    statistics.add_trip(Trip::new(finished_car));
+
+    if finished_car.get_vehicle_type() == VehicleType::Emergency {
+        statistics.set_emergency_vehicles(statistics.get_emergency_vehicles() + 1.0);
+        statistics.set_emergency_delay(statistics.get_emergency_delay() + finished_car.get_delay());
+
+        if statistics.get_max_emergency_delay() <= finished_car.get_delay() {
+            statistics.set_max_emergency_delay(finished_car.get_delay())
+        }
+    }
 }
 
 fn get_max_min_speed(car: &Car, global_state: &mut GlobalState) {
-    let mut statistics = global_state.get_statistics();
+    let statistics = global_state.get_statistics_mut();
+
+    if car.get_vehicle_type() != VehicleType::Bicycle {
+        if statistics.get_max_speed() <= car.get_speed() {
+            statistics.set_max_speed(car.get_speed())
+        }
 
-    if statistics.get_max_speed() <= car.get_speed() {
-        statistics.set_max_speed(car.get_speed())
+        if statistics.get_min_speed() == -0.1
+            || statistics.get_min_speed() > car.get_speed() && car.get_speed() != 0.0
+        {
+            statistics.set_min_speed(car.get_speed())
+        }
     }
 
-    if statistics.get_min_speed() == -0.1
-        || statistics.get_min_speed() > car.get_speed() && car.get_speed() != 0.0
-    {
-        statistics.set_min_speed(car.get_speed())
+    let mut class_stats = statistics.get_class_statistics(car.get_vehicle_type());
+
+    if class_stats.max_speed <= car.get_speed() {
+        class_stats.max_speed = car.get_speed()
     }
 
-    global_state.set_statistics(statistics)
+    statistics.set_class_statistics(car.get_vehicle_type(), class_stats);
 }
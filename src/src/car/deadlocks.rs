@@ -0,0 +1,266 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    cars::{compare_car_statistics, Cars},
+    Car,
+};
+use crate::constants::CONFIG_FILE;
+use crate::state::GlobalState;
+
+/// How a detected deadlock is broken up
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+pub enum DeadlockRecovery {
+    /// Only reports the deadlocks
+    None,
+    /// The longest waiting vehicle squeezes past the stopped vehicle it yields to once the space
+    /// in front of it is free, until it is through that conflict. A gridlock that outlasts
+    /// `DeadlockConfig::tow_time` is broken up as with `Remove`
+    LongestWaiting,
+    /// The longest waiting vehicle is towed away
+    Remove,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeadlockConfig {
+    /// Seconds the vehicles of a cycle in the wait-for graph have to stand still before it
+    /// counts as a deadlock
+    pub cycle_time: f32,
+    /// Seconds without any vehicle moving before the intersection counts as stalled
+    pub stall_time: f32,
+    pub recovery: DeadlockRecovery,
+    /// Seconds a deadlock may last after it was found before `LongestWaiting` tows a vehicle
+    /// away, never when zero
+    pub tow_time: f32,
+}
+
+impl Default for DeadlockConfig {
+    fn default() -> Self {
+        DeadlockConfig {
+            cycle_time: 3.0,
+            stall_time: 20.0,
+            recovery: DeadlockRecovery::None,
+            tow_time: 10.0,
+        }
+    }
+}
+
+impl DeadlockConfig {
+    /// Falls back to the default times when the configured ones are shorter than a frame, the
+    /// detection counts in frames and divides by them
+    pub fn validate(&mut self) {
+        let default = DeadlockConfig::default();
+
+        for (field, time, default) in [
+            ("cycle_time", &mut self.cycle_time, default.cycle_time),
+            ("stall_time", &mut self.stall_time, default.stall_time),
+        ] {
+            if *time < 1.0 / 60.0 || time.is_nan() {
+                eprintln!(
+                    "Ignoring deadlocks.{field} in {CONFIG_FILE}: {time} is shorter than a frame"
+                );
+                *time = default;
+            }
+        }
+    }
+}
+
+/// Deadlocks found so far and how they were resolved
+#[derive(Debug, Default, Clone, PartialEq, Copy, Serialize)]
+pub struct DeadlockStatistics {
+    /// Cycles in the wait-for graph that stood still for `DeadlockConfig::cycle_time`
+    pub deadlocks: f32,
+    /// Times no vehicle moved for `DeadlockConfig::stall_time`
+    pub stalls: f32,
+    /// Most vehicles in one cycle
+    pub largest_cycle: f32,
+    /// Seconds with a deadlock or a stall going on
+    pub deadlocked_time: f32,
+    /// Vehicles given priority to break a deadlock
+    pub prioritized: f32,
+    /// Vehicles towed away to break a deadlock
+    pub removed: f32,
+}
+
+/// Cycles of vehicles waiting for each other and how long nothing has moved
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DeadlockDetector {
+    /// IDs of the vehicles of every current cycle, sorted, with the seconds it has lasted
+    cycles: Vec<(Vec<u64>, f32)>,
+    /// Seconds since a vehicle last moved
+    stall_time: f32,
+}
+
+impl DeadlockDetector {
+    pub fn new() -> Self {
+        DeadlockDetector {
+            cycles: Vec::new(),
+            stall_time: 0.0,
+        }
+    }
+
+    //getters
+    /// IDs of the vehicles in a deadlock
+    pub fn get_deadlocked(&self, config: &DeadlockConfig) -> Vec<u64> {
+        self.cycles
+            .iter()
+            .filter(|(_, time)| *time >= config.cycle_time)
+            .flat_map(|(cycle, _)| cycle.iter().copied())
+            .collect()
+    }
+}
+
+/// Cycles in the wait-for graph of the standing vehicles. Every vehicle waits for at most the
+/// one vehicle its stop reason names, so following those from every vehicle finds all of them.
+pub fn find_cycles(cars: &Cars) -> Vec<Vec<u64>> {
+    let blocker = |id: u64| {
+        cars.iter()
+            .find(|car| car.get_id() == id && car.get_speed() == 0.0)
+            .and_then(|car| car.get_stop_reason())
+            .and_then(|reason| reason.get_blocker())
+    };
+    let mut cycles: Vec<Vec<u64>> = Vec::new();
+
+    for car in cars.iter() {
+        let mut path = vec![car.get_id()];
+
+        while let Some(next) = blocker(*path.last().unwrap()) {
+            if let Some(start) = path.iter().position(|id| *id == next) {
+                let mut cycle = path.split_off(start);
+                cycle.sort();
+                if !cycles.contains(&cycle) {
+                    cycles.push(cycle);
+                }
+                break;
+            }
+            path.push(next);
+        }
+    }
+    cycles
+}
+
+/// Finds the deadlocks and stalls, reports them in the statistics and breaks them up as
+/// `DeadlockConfig::recovery` says, called every frame
+pub fn resolve_deadlocks(global_state: &mut GlobalState) {
+    let config = global_state.get_config().deadlocks.clone();
+    let mut cars = global_state.get_cars();
+    let mut detector = global_state.get_deadlock_detector().clone();
+    let mut statistics = global_state.get_statistics().get_deadlocks();
+    // Vehicles to break up with how long they have been deadlocked
+    let mut stuck: Vec<(Vec<u64>, f32)> = Vec::new();
+
+    // Priority ends once the vehicle it was given over drives on or is gone, or the vehicle has
+    // reached its turn point and is past the conflict
+    let others = cars.clone();
+    for car in cars.iter_mut() {
+        let still_blocked = car.get_priority().is_some_and(|id| {
+            !car.is_leaving_intersection()
+                && others
+                    .iter()
+                    .any(|other| other.get_id() == id && other.get_speed() == 0.0)
+        });
+        if !still_blocked {
+            car.set_priority(None);
+        }
+    }
+
+    // Cycles last as long as the same vehicles keep waiting for each other
+    let cycles = find_cycles(&cars)
+        .into_iter()
+        .map(|cycle| {
+            let time = detector
+                .cycles
+                .iter()
+                .find(|(other, _)| *other == cycle)
+                .map_or(0.0, |(_, time)| time + 1.0 / 60.0);
+            (cycle, time)
+        })
+        .collect::<Vec<_>>();
+
+    for (cycle, time) in cycles.iter() {
+        let previous = time - 1.0 / 60.0;
+        if *time >= config.cycle_time && previous < config.cycle_time {
+            statistics.deadlocks += 1.0;
+            statistics.largest_cycle = statistics.largest_cycle.max(cycle.len() as f32);
+        }
+
+        // Recovers once the deadlock is found and again whenever it lasts another
+        // `cycle_time`
+        if *time >= config.cycle_time
+            && (time / config.cycle_time).floor() > (previous / config.cycle_time).floor()
+        {
+            stuck.push((cycle.clone(), time - config.cycle_time));
+        }
+    }
+    detector.cycles = cycles;
+
+    if !cars.is_empty() && cars.iter().all(|car| car.get_speed() == 0.0) {
+        detector.stall_time += 1.0 / 60.0;
+    } else {
+        detector.stall_time = 0.0;
+    }
+    let stall = detector.stall_time;
+    if stall >= config.stall_time
+        && (stall / config.stall_time).floor() > ((stall - 1.0 / 60.0) / config.stall_time).floor()
+    {
+        statistics.stalls += 1.0;
+        stuck.push((
+            cars.iter().map(|car| car.get_id()).collect(),
+            stall - config.stall_time,
+        ));
+    }
+
+    if !detector.get_deadlocked(&config).is_empty() || stall >= config.stall_time {
+        statistics.deadlocked_time += 1.0 / 60.0;
+    }
+
+    for (ids, time) in stuck {
+        let recovery = match config.recovery {
+            DeadlockRecovery::LongestWaiting
+                if config.tow_time > 0.0 && time >= config.tow_time =>
+            {
+                DeadlockRecovery::Remove
+            }
+            recovery => recovery,
+        };
+        let candidate = cars
+            .iter()
+            .filter(|car| ids.contains(&car.get_id()) && can_recover(car, recovery))
+            .max_by(|a, b| a.get_delay().total_cmp(&b.get_delay()))
+            .map(|car| car.get_id());
+
+        match (recovery, candidate) {
+            (DeadlockRecovery::LongestWaiting, Some(id)) => {
+                if let Some(car) = cars.iter_mut().find(|car| car.get_id() == id) {
+                    car.set_priority(car.get_stop_reason().and_then(|reason| reason.get_blocker()));
+                    statistics.prioritized += 1.0;
+                }
+            }
+            (DeadlockRecovery::Remove, Some(id)) => {
+                // The towed vehicle ends its trip where it stood
+                if let Some(car) = cars.iter().find(|car| car.get_id() == id) {
+                    compare_car_statistics(car, global_state);
+                }
+                cars.retain(|car| car.get_id() != id);
+                statistics.removed += 1.0;
+            }
+            _ => {}
+        }
+    }
+
+    global_state.set_cars(cars);
+    global_state.set_deadlock_detector(detector);
+    global_state.get_statistics_mut().set_deadlocks(statistics);
+}
+
+/// Vehicles queued behind another vehicle of their lane can't get out of its way, priority
+/// only helps the ones waiting for crossing traffic that don't have it over that vehicle yet.
+/// Any vehicle can be towed away.
+fn can_recover(car: &Car, recovery: DeadlockRecovery) -> bool {
+    match recovery {
+        DeadlockRecovery::LongestWaiting => car.get_stop_reason().is_some_and(|reason| {
+            reason.yields_to_vehicle() && car.get_priority() != reason.get_blocker()
+        }),
+        _ => true,
+    }
+}
@@ -0,0 +1,209 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Distribution;
+use crate::constants::{CONFIG_FILE, MAX_PERCEPTION_DELAY};
+use crate::random;
+
+/// Driving styles a driver is sampled from when the vehicle spawns
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+pub enum DriverProfile {
+    Cautious,
+    Normal,
+    Aggressive,
+    Random,
+}
+
+impl DriverProfile {
+    /// Every spawnable profile, in the order of `DriverConfig::weights`.
+    pub const ALL: [DriverProfile; 3] = [
+        DriverProfile::Cautious,
+        DriverProfile::Normal,
+        DriverProfile::Aggressive,
+    ];
+
+    pub fn select_random(config: &DriverConfig) -> DriverProfile {
+        let mut rng = random::rng();
+        let total: u32 = config.weights.iter().sum();
+        if total == 0 {
+            return DriverProfile::Normal;
+        }
+        let mut roll = rng.gen_range(0..total);
+
+        for (i, weight) in config.weights.iter().enumerate() {
+            if roll < *weight {
+                return Self::ALL[i];
+            }
+            roll -= weight;
+        }
+        unreachable!()
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            DriverProfile::Cautious => "Cautious",
+            DriverProfile::Normal => "Normal",
+            DriverProfile::Aggressive => "Aggressive",
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Behaviour of the driver of a single vehicle, sampled once at spawn
+#[derive(Debug, Clone, PartialEq, Copy)]
+pub struct Driver {
+    profile: DriverProfile,
+    desired_speed: f32,
+    headway: f32,
+    reaction_delay: f32,
+    gap_acceptance: f32,
+    perception_delay: f32,
+}
+
+impl Driver {
+    pub fn new(mut profile: DriverProfile, config: &DriverConfig) -> Self {
+        if profile == DriverProfile::Random {
+            profile = DriverProfile::select_random(config);
+        }
+
+        let settings = match profile {
+            DriverProfile::Cautious => config.cautious,
+            DriverProfile::Normal => config.normal,
+            DriverProfile::Aggressive => config.aggressive,
+            _ => unreachable!(),
+        };
+
+        Driver {
+            profile,
+            desired_speed: settings.desired_speed.sample(),
+            headway: settings.headway.sample(),
+            reaction_delay: settings.reaction_delay.sample(),
+            gap_acceptance: settings.gap_acceptance.sample(),
+            perception_delay: settings
+                .perception_delay
+                .sample()
+                .clamp(0.0, MAX_PERCEPTION_DELAY),
+        }
+    }
+
+    //getters
+    pub fn get_profile(&self) -> DriverProfile {
+        self.profile
+    }
+
+    pub fn get_desired_speed(&self) -> f32 {
+        self.desired_speed
+    }
+
+    pub fn get_headway(&self) -> f32 {
+        self.headway
+    }
+
+    pub fn get_reaction_delay(&self) -> f32 {
+        self.reaction_delay
+    }
+
+    pub fn get_gap_acceptance(&self) -> f32 {
+        self.gap_acceptance
+    }
+
+    pub fn get_perception_delay(&self) -> f32 {
+        self.perception_delay
+    }
+}
+
+/// Mix of driver profiles and the distributions each one samples its driver from
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DriverConfig {
+    /// Spawn weights of the cautious, normal and aggressive profiles
+    pub weights: [u32; 3],
+    pub cautious: ProfileConfig,
+    pub normal: ProfileConfig,
+    pub aggressive: ProfileConfig,
+}
+
+impl Default for DriverConfig {
+    fn default() -> Self {
+        let distribution = Distribution::new;
+
+        DriverConfig {
+            weights: [2, 6, 2],
+            cautious: ProfileConfig {
+                desired_speed: distribution(0.85, 0.05, 0.7, 0.95),
+                headway: distribution(1.3, 0.1, 1.1, 1.6),
+                reaction_delay: distribution(0.9, 0.2, 0.5, 1.5),
+                gap_acceptance: distribution(1.15, 0.05, 1.0, 1.3),
+                perception_delay: Distribution::default(),
+            },
+            normal: ProfileConfig::default(),
+            aggressive: ProfileConfig {
+                desired_speed: distribution(1.1, 0.05, 1.0, 1.2),
+                headway: distribution(0.8, 0.05, 0.7, 0.9),
+                reaction_delay: distribution(0.4, 0.1, 0.2, 0.7),
+                gap_acceptance: distribution(0.9, 0.03, 0.85, 0.95),
+                perception_delay: Distribution::default(),
+            },
+        }
+    }
+}
+
+impl DriverConfig {
+    /// Replaces every distribution that can't be sampled by the default of its profile
+    pub fn validate(&mut self) {
+        let default = DriverConfig::default();
+
+        for (name, profile, default) in [
+            ("cautious", &mut self.cautious, default.cautious),
+            ("normal", &mut self.normal, default.normal),
+            ("aggressive", &mut self.aggressive, default.aggressive),
+        ] {
+            for (field, distribution, default) in [
+                ("desired_speed", &mut profile.desired_speed, default.desired_speed),
+                ("headway", &mut profile.headway, default.headway),
+                ("reaction_delay", &mut profile.reaction_delay, default.reaction_delay),
+                ("gap_acceptance", &mut profile.gap_acceptance, default.gap_acceptance),
+                ("perception_delay", &mut profile.perception_delay, default.perception_delay),
+            ] {
+                if !distribution.is_valid() {
+                    eprintln!(
+                        "Ignoring drivers.{name}.{field} in {CONFIG_FILE}: min is above max or \
+                         std_dev is negative"
+                    );
+                    *distribution = default;
+                }
+            }
+        }
+    }
+}
+
+/// Distributions a driver of one profile is sampled from, missing fields are taken from the
+/// normal profile
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileConfig {
+    /// Multiple of the vehicle's max speed the driver wants to drive at
+    pub desired_speed: Distribution,
+    /// Multiple of the safe distance kept to the vehicle in front
+    pub headway: Distribution,
+    /// Seconds it takes the driver to pull away once the way is clear
+    pub reaction_delay: Distribution,
+    /// Multiple of how far ahead the driver checks for crossing traffic, smaller values accept
+    /// tighter gaps
+    pub gap_acceptance: Distribution,
+    /// Seconds old the positions of the other vehicles are when the driver decides on its speed,
+    /// up to `MAX_PERCEPTION_DELAY`. Drivers react to the current frame by default.
+    pub perception_delay: Distribution,
+}
+
+impl Default for ProfileConfig {
+    fn default() -> Self {
+        ProfileConfig {
+            desired_speed: Distribution::new(1.0, 0.05, 0.9, 1.1),
+            headway: Distribution::new(1.0, 0.1, 0.8, 1.2),
+            reaction_delay: Distribution::new(0.6, 0.15, 0.3, 1.0),
+            gap_acceptance: Distribution::new(1.0, 0.05, 0.9, 1.1),
+            perception_delay: Distribution::default(),
+        }
+    }
+}
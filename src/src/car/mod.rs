@@ -1,14 +1,35 @@
 pub mod cars;
+pub mod deadlocks;
+pub mod drivers;
+pub mod sensors;
+pub mod vehicles;
 
-use crate::constants::SPRITE_CARS;
+use self::cars::Cars;
+use self::drivers::{Driver, DriverProfile};
+use self::vehicles::VehicleType;
+use crate::constants::{BRAKING, CONTROL_DISTANCE, PATH_MARGIN, SPRITE_CARS};
+use crate::intersection::control::{check_policy, get_distance_to_stop_line};
+use crate::intersection::dimensions::Dimensions;
+use crate::intersection::Point;
+use crate::pedestrian::PedestrianState;
+use crate::random;
 use crate::state::GlobalState;
-use macroquad::prelude::{draw_texture_ex, Color, DrawTextureParams, Rect, Vec2};
+use crate::v2i::{holds_grant, manager::conflicts, uses_v2i, Permission};
+use macroquad::prelude::{
+    draw_circle_lines, draw_line, draw_rectangle_ex, draw_rectangle_lines, draw_text_ex,
+    draw_texture_ex, draw_triangle, Color, DrawRectangleParams, DrawTextureParams, Rect,
+    TextParams, Vec2, BLUE, GREEN, MAGENTA, ORANGE, RED, SKYBLUE, WHITE, YELLOW,
+};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::f32::consts::PI;
 
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub struct Car {
+    id: u64,
     sprite: (f32, f32, f32, f32),
+    vehicle_type: VehicleType,
+    driver: Driver,
     x: f32,
     y: f32,
     rotation: f32,
@@ -20,31 +41,97 @@ pub struct Car {
     lane: Lane,
     turn_offset: f32,
     driving_time: f32,
+    delay: f32,
+    /// Part of the delay lost because of the intersection, see `is_in_control_area`
+    control_delay: f32,
+    /// Times the car has come to a standstill
+    stops: u32,
+    /// Seconds the car has been standing still while allowed to drive
+    reaction_time: f32,
+    permission: Permission,
+    /// Seconds since the car last sent a message to the intersection manager
+    message_time: f32,
+    /// Overrides set by the external controller, see `controller::Command`
+    commanded_speed: Option<f32>,
+    commanded_permission: Option<bool>,
+    /// Why the car wants to stop this frame, if it does
+    stop_reason: Option<StopReason>,
+    /// Vehicle it may squeeze past to break a deadlock, see
+    /// `deadlocks::DeadlockRecovery::LongestWaiting`
+    priority: Option<u64>,
 }
 
 impl Car {
-    pub fn new(mut direction: Direction, global_state: &GlobalState) -> Self {
-        let mut rng = rand::thread_rng();
+    /// Vehicle entering from `direction`, of a random type when `vehicle_type` is `None`
+    pub fn new(
+        mut direction: Direction,
+        vehicle_type: Option<VehicleType>,
+        global_state: &GlobalState,
+    ) -> Self {
+        if direction == Direction::Random {
+            direction = Self::select_random_direction();
+        }
+
+        let vehicle_type = vehicle_type.unwrap_or_else(VehicleType::select_random);
+
+        let lane = if vehicle_type == VehicleType::Bicycle {
+            Lane::Bike
+        } else {
+            Self::select_random_lane()
+        };
+
+        Self::new_in_lane(direction, Some(vehicle_type), lane, global_state)
+    }
+
+    /// Vehicle entering from the given lane. Cyclists only ride in the bike lane and only
+    /// cyclists do, a random vehicle type, `None`, follows the lane.
+    pub fn new_in_lane(
+        direction: Direction,
+        vehicle_type: Option<VehicleType>,
+        mut lane: Lane,
+        global_state: &GlobalState,
+    ) -> Self {
+        let mut rng = random::rng();
         let s = rng.gen_range(0..20);
         let sprite = SPRITE_CARS[s];
         let intersection = global_state.get_intersection();
         let dimensions = intersection.get_dimensions();
         let center = dimensions.get_center();
-        let lane = Self::select_random_lane();
         let x_max = dimensions.get_x_max();
         let y_max = dimensions.get_y_max();
 
-        if direction == Direction::Random {
-            direction = Self::select_random_direction();
-        }
+        let vehicle_type = match (vehicle_type, lane) {
+            (None, Lane::Bike) => VehicleType::Bicycle,
+            (None, _) => VehicleType::select_random(),
+            (Some(VehicleType::Bicycle), _) => {
+                lane = Lane::Bike;
+                VehicleType::Bicycle
+            }
+            (Some(vehicle_type), Lane::Bike) => {
+                lane = Self::select_random_lane();
+                vehicle_type
+            }
+            (Some(vehicle_type), _) => vehicle_type,
+        };
+
+        // Driving styles are sampled for motorised vehicles, cyclists all ride alike
+        let driver_profile = if vehicle_type == VehicleType::Bicycle {
+            DriverProfile::Normal
+        } else {
+            DriverProfile::Random
+        };
 
         Car {
+            id: global_state.get_next_car_id(),
             sprite,
+            vehicle_type,
+            driver: Driver::new(driver_profile, &global_state.get_config().drivers),
             x: match direction {
                 Direction::South | Direction::North => match lane {
                     Lane::Left => intersection.get_lanes(direction).left_axis,
                     Lane::Middle => intersection.get_lanes(direction).middle_axis,
                     Lane::Right => intersection.get_lanes(direction).right_axis,
+                    Lane::Bike => intersection.get_lanes(direction).bike_axis,
                 },
                 Direction::West => center.x + x_max,
                 Direction::East => center.x - x_max,
@@ -55,6 +142,7 @@ impl Car {
                     Lane::Left => intersection.get_lanes(direction).left_axis,
                     Lane::Middle => intersection.get_lanes(direction).middle_axis,
                     Lane::Right => intersection.get_lanes(direction).right_axis,
+                    Lane::Bike => intersection.get_lanes(direction).bike_axis,
                 },
                 Direction::North => center.y + y_max,
                 Direction::South => center.y - y_max,
@@ -69,29 +157,39 @@ impl Car {
             },
             leaving_intersection: false,
             target_speed: 1.0,
-            speed: 0.0, // TODO: Different speeds
+            speed: 0.0,
             direction_from: direction,
             current_direction: direction,
             lane,
             turn_offset: match direction {
                 Direction::North | Direction::West => match lane {
                     Lane::Right => 2.5 * dimensions.get_lane_width(),
-                    Lane::Middle => 0.0,
+                    Lane::Middle | Lane::Bike => 0.0,
                     Lane::Left => -0.5 * dimensions.get_lane_width(),
                 },
                 Direction::South | Direction::East => match lane {
                     Lane::Right => -2.5 * dimensions.get_lane_width(),
-                    Lane::Middle => 0.0,
+                    Lane::Middle | Lane::Bike => 0.0,
                     Lane::Left => 0.5 * dimensions.get_lane_width(),
                 },
                 _ => unreachable!(),
             },
             driving_time: 0.0,
+            delay: 0.0,
+            control_delay: 0.0,
+            stops: 0,
+            reaction_time: 0.0,
+            permission: Permission::None,
+            message_time: 0.0,
+            commanded_speed: None,
+            commanded_permission: None,
+            stop_reason: None,
+            priority: None,
         }
     }
 
     fn select_random_lane() -> Lane {
-        let mut rng = ::rand::thread_rng();
+        let mut rng = random::rng();
         match rng.gen_range(0..7) {
             0 => Lane::Left,
             1 => Lane::Middle,
@@ -106,7 +204,7 @@ impl Car {
     }
 
     fn select_random_direction() -> Direction {
-        let mut rng = ::rand::thread_rng();
+        let mut rng = random::rng();
         match rng.gen_range(0..4) {
             0 => Direction::North,
             1 => Direction::South,
@@ -127,547 +225,992 @@ impl Car {
     }
 
     pub fn adjust_speed(&mut self, global_state: &GlobalState) {
-        if self.same_lane_is_clear(global_state) {
-            self.set_target_speed(2.2);
-            if self.is_at_intersection(global_state) {
-                self.set_target_speed(1.2);
-
-                if self.crossing_lane_is_clear(global_state) {
-                    self.set_target_speed(1.2)
-                } else {
-                    self.set_target_speed(0.0);
+        let desired_speed = self.driver.get_desired_speed();
+        let max_speed = desired_speed * self.vehicle_type.get_max_speed();
+        let intersection_speed = desired_speed
+            * self
+                .vehicle_type
+                .get_intersection_speed(self.lane == Lane::Left || self.lane == Lane::Right);
+
+        self.stop_reason = None;
+
+        match self.same_lane_is_clear(global_state) {
+            Ok(()) => {
+                self.set_target_speed(max_speed);
+                if self.is_at_intersection(global_state) {
+                    self.set_target_speed(intersection_speed);
+
+                    if let Err(reason) = self
+                        .crossing_lane_is_clear(global_state)
+                        .and_then(|_| self.turn_is_clear(global_state))
+                    {
+                        self.set_target_speed(0.0);
+                        self.stop_reason = Some(reason);
+                    }
                 }
             }
-        } else {
+            Err(reason) => {
+                self.set_target_speed(0.0);
+                self.stop_reason = Some(reason);
+            }
+        }
+
+        let policy = check_policy(self, global_state)
+            .and_then(|_| self.crosswalk_is_clear(global_state))
+            .and_then(|_| self.cyclists_are_clear(global_state));
+
+        if let Err(reason) = policy {
             self.set_target_speed(0.0);
+            self.stop_reason = Some(reason);
         }
 
-        if self.get_speed() < self.get_target_speed() {
-            self.set_speed(self.get_speed() + 0.15)
-        } else if self.get_speed() > self.get_target_speed() && self.get_speed() > 0.15 {
-            self.set_speed(self.get_speed() - 0.15)
+        if self.stop_reason.is_none() {
+            if let Err(reason) = self.path_is_clear(global_state) {
+                self.set_target_speed(0.0);
+                self.stop_reason = Some(reason);
+            }
+        }
+
+        if let Some(speed) = self.commanded_speed {
+            self.set_target_speed(self.get_target_speed().min(speed));
+            if speed == 0.0 && self.stop_reason.is_none() {
+                self.stop_reason = Some(StopReason::Controller);
+            }
+        }
+
+        // Pulling away from a stop only happens once the driver has reacted
+        if self.get_speed() == 0.0 && self.get_target_speed() > 0.0 {
+            self.reaction_time += 1.0 / 60.0;
+            if self.reaction_time < self.driver.get_reaction_delay() {
+                self.set_target_speed(0.0);
+                self.stop_reason = Some(StopReason::Reacting);
+            }
         } else {
+            self.reaction_time = 0.0;
+        }
+
+        let was_moving = self.get_speed() > 0.0;
+
+        if self.get_speed() < self.get_target_speed() {
+            self.set_speed(
+                (self.get_speed() + self.vehicle_type.get_acceleration() / 60.0)
+                    .min(self.get_target_speed()),
+            )
+        } else if self.get_speed() > self.get_target_speed() && self.get_speed() > BRAKING / 60.0 {
+            self.set_speed(self.get_speed() - BRAKING / 60.0)
+        } else if self.get_speed() > self.get_target_speed() {
             self.set_speed(0.0);
         }
 
+        if was_moving && self.get_speed() == 0.0 {
+            self.stops += 1;
+        }
+
+        // Time lost compared to driving at the free flow speed
+        let free_speed = if self.is_at_intersection(global_state) {
+            intersection_speed
+        } else {
+            max_speed
+        };
+        let delay = (1.0 - self.get_speed() / free_speed).max(0.0) / 60.0;
+        self.delay += delay;
+        if self.is_in_control_area(global_state) {
+            self.control_delay += delay;
+        }
+
         self.update_coords(global_state)
     }
 
     fn update_coords(&mut self, global_state: &GlobalState) {
         let intersection = global_state.get_intersection();
-        let speed_unit = global_state
-            .get_intersection()
-            .get_dimensions()
-            .get_speed_unit();
+        let step = self.get_speed() / 60.0;
 
         match self.current_direction {
             Direction::North => {
-                self.y = self.y - speed_unit * self.get_speed();
+                self.y = self.y - step;
                 match self.lane {
                     Lane::Left => self.x = intersection.get_lanes(Direction::North).left_axis,
                     Lane::Middle => self.x = intersection.get_lanes(Direction::North).middle_axis,
                     Lane::Right => self.x = intersection.get_lanes(Direction::North).right_axis,
+                    Lane::Bike => self.x = intersection.get_lanes(Direction::North).bike_axis,
                 }
             }
             Direction::East => {
-                self.x = self.x + speed_unit * self.get_speed();
+                self.x = self.x + step;
                 match self.lane {
                     Lane::Left => self.y = intersection.get_lanes(Direction::East).left_axis,
                     Lane::Middle => self.y = intersection.get_lanes(Direction::East).middle_axis,
                     Lane::Right => self.y = intersection.get_lanes(Direction::East).right_axis,
+                    Lane::Bike => self.y = intersection.get_lanes(Direction::East).bike_axis,
                 }
             }
             Direction::South => {
-                self.y = self.y + speed_unit * self.get_speed();
+                self.y = self.y + step;
                 match self.lane {
                     Lane::Left => self.x = intersection.get_lanes(Direction::South).left_axis,
                     Lane::Middle => self.x = intersection.get_lanes(Direction::South).middle_axis,
                     Lane::Right => self.x = intersection.get_lanes(Direction::South).right_axis,
+                    Lane::Bike => self.x = intersection.get_lanes(Direction::South).bike_axis,
                 }
             }
             Direction::West => {
-                self.x = self.x - speed_unit * self.get_speed();
+                self.x = self.x - step;
                 match self.lane {
                     Lane::Left => self.y = intersection.get_lanes(Direction::West).left_axis,
                     Lane::Middle => self.y = intersection.get_lanes(Direction::West).middle_axis,
                     Lane::Right => self.y = intersection.get_lanes(Direction::West).right_axis,
+                    Lane::Bike => self.y = intersection.get_lanes(Direction::West).bike_axis,
                 }
             }
             _ => unreachable!(),
         };
     }
 
-    pub fn same_lane_is_clear(&mut self, global_state: &GlobalState) -> bool {
+    /// Whether the car keeps a safe distance to the car in front of it, `StopReason::CarAhead`
+    /// otherwise
+    pub fn same_lane_is_clear(&mut self, global_state: &GlobalState) -> Result<(), StopReason> {
+        let cars = self.get_perceived_cars(global_state);
+        self.same_lane_is_clear_of(cars, global_state)
+    }
+
+    /// `same_lane_is_clear` against the given cars instead of the ones the driver perceives
+    pub fn same_lane_is_clear_of<'a>(
+        &mut self,
+        cars: impl IntoIterator<Item = &'a Car>,
+        global_state: &GlobalState,
+    ) -> Result<(), StopReason> {
         let direction = self.get_current_direction();
         let dimensions = global_state.get_intersection().get_dimensions();
-        let safety_distance = dimensions.get_safety_distance();
         let car_width = dimensions.get_car_width();
-        let cars = global_state.get_cars();
-
-        let safe_distance = |x: f32| -> f32 {
-            if self.is_at_intersection(global_state) {
-                return x;
-            } else {
-                return 1.0 * dimensions.get_lane_width();
-            }
-        };
+        let safe_distance = self.get_safe_distance(global_state);
 
         for car in cars {
             let speed = car.get_speed();
-            if car.get_current_direction() == self.get_current_direction()
+            if car.id != self.id
+                && car.get_current_direction() == self.get_current_direction()
                 && car.get_lane() == self.get_lane()
             {
-                match direction {
-                    Direction::South => {
-                        if car.get_y()
-                            < self.get_y() + 1.0 * car_width + safe_distance(safety_distance * 1.8)
-                            && car.get_y() > self.get_y()
-                            && car.get_y() != self.get_y()
-                        {
-                            self.set_target_speed(speed - speed * 0.1);
-                            return false;
-                        }
-                    }
-                    Direction::West => {
-                        if car.get_x()
-                            > self.get_x() - 1.0 * car_width - safe_distance(safety_distance * 1.8)
-                            && car.get_x() < self.get_x()
-                            && car.get_x() != self.get_x()
-                        {
-                            self.set_target_speed(speed - speed * 0.1);
-                            return false;
-                        }
-                    }
-                    Direction::North => {
-                        if car.get_y()
-                            > self.get_y() - 1.0 * car_width - safe_distance(safety_distance * 1.5)
-                            && car.get_y() < self.get_y()
-                            && car.get_y() != self.get_y()
-                        {
-                            self.set_target_speed(speed - speed * 0.1);
-                            return false;
-                        }
-                    }
-                    Direction::East => {
-                        if car.get_x()
-                            < self.get_x() + 1.0 * car_width + safe_distance(safety_distance * 1.5)
-                            && car.get_x() > self.get_x()
-                            && car.get_x() != self.get_x()
-                        {
-                            self.set_target_speed(speed - speed * 0.1);
-                            return false;
-                        }
-                    }
-
+                // How far the other car is in front of self
+                let ahead = match direction {
+                    Direction::South => car.get_y() - self.get_y(),
+                    Direction::West => self.get_x() - car.get_x(),
+                    Direction::North => self.get_y() - car.get_y(),
+                    Direction::East => car.get_x() - self.get_x(),
                     _ => unreachable!(),
+                };
+
+                // Gap left once both cars would have braked to a stop
+                let gap = ahead + Self::get_braking_distance(speed)
+                    - Self::get_braking_distance(self.get_speed());
+
+                if ahead > 0.0 && gap < self.get_clearance(car, car_width) + safe_distance {
+                    self.set_target_speed(speed - speed * 0.1);
+                    return Err(StopReason::CarAhead(car.id, car.lane));
                 }
             }
         }
-        return true;
+        return Ok(());
     }
 
-    pub fn crossing_lane_is_clear(&mut self, global_state: &GlobalState) -> bool {
-        let direction = self.get_current_direction();
+    /// Distance kept to the car in front on top of `get_clearance`, shorter inside the
+    /// intersection
+    fn get_safe_distance(&self, global_state: &GlobalState) -> f32 {
+        let dimensions = global_state.get_intersection().get_dimensions();
+        let headway = self.driver.get_headway();
+
+        if !self.is_at_intersection(global_state) {
+            return headway * dimensions.get_lane_width();
+        }
+
+        let safety_factor = match self.current_direction {
+            Direction::South | Direction::West => 1.8,
+            Direction::North | Direction::East => 1.5,
+            _ => unreachable!(),
+        };
+        headway * dimensions.get_safety_distance() * safety_factor
+    }
+
+    /// Area in front of the car that `same_lane_is_clear` keeps free of a stopped car of the
+    /// same type, at the car's current speed
+    pub fn get_safety_envelope(&self, global_state: &GlobalState) -> Rect {
         let dimensions = global_state.get_intersection().get_dimensions();
-        let center = dimensions.get_center();
         let car_width = dimensions.get_car_width();
-        let lane_width = dimensions.get_lane_width();
-        let cars = global_state.get_cars();
+        let extent = self.get_extent();
+        let reach = self.get_clearance(self, car_width)
+            + self.get_safe_distance(global_state)
+            + Self::get_braking_distance(self.speed)
+            + self.vehicle_type.get_length() / 2.0;
+
+        match self.current_direction {
+            Direction::North => Rect::new(self.x - extent.x, self.y - reach, 2.0 * extent.x, reach),
+            Direction::South => Rect::new(self.x - extent.x, self.y, 2.0 * extent.x, reach),
+            Direction::West => Rect::new(self.x - reach, self.y - extent.y, reach, 2.0 * extent.y),
+            Direction::East => Rect::new(self.x, self.y - extent.y, reach, 2.0 * extent.y),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Point at which the car turns into its new direction, until it has turned
+    pub fn get_turn_point(&self, global_state: &GlobalState) -> Option<Vec2> {
+        if self.leaving_intersection {
+            return None;
+        }
+
+        let center = global_state
+            .get_intersection()
+            .get_dimensions()
+            .get_center();
+        match self.current_direction {
+            Direction::North | Direction::South => {
+                Some(Vec2::new(self.x, center.y + self.turn_offset))
+            }
+            Direction::West | Direction::East => {
+                Some(Vec2::new(center.x + self.turn_offset, self.y))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Whether no car is in the way through the intersection, `StopReason::CrossingTraffic`
+    /// with the first car found otherwise
+    pub fn crossing_lane_is_clear(&mut self, global_state: &GlobalState) -> Result<(), StopReason> {
+        let dimensions = global_state.get_intersection().get_dimensions();
+        let car_width = dimensions.get_car_width();
+        let cars = self.get_perceived_cars(global_state);
+
+        let look_ahead = match self.get_look_ahead(dimensions) {
+            Some(look_ahead) => look_ahead,
+            None => return Ok(()),
+        };
+
+        let granted = holds_grant(self, global_state);
+        let movement = (self.direction_from, self.lane);
 
         for car in cars {
-            let is_not_self = car.get_y() != self.get_y() && car.get_x() != self.get_x();
-            match direction {
-                Direction::North => match self.lane {
-                    Lane::Left => {
-                        // Self is on the intersection but hasn't crossed middle
-                        if self.get_y() < center.y + 3.0 * lane_width && self.get_y() > center.y {
-                            if car.get_y() > self.get_y() - 2.0 * car_width - 1.2 * lane_width // look ahead n number of lanes
-                            && car.get_y() < self.get_y() - 0.6 * car_width  // front of car
-                            && car.get_x() > self.get_x() - 0.6 * lane_width  // left hand side
-                            && car.get_x() < self.get_x() + 0.7 * lane_width  // right hand side
-                            && is_not_self
-                            && (car.get_current_direction() == Direction::West || car.get_current_direction() == Direction::East)
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-                        // crossed the middle of intersection
-                        if self.get_y() < center.y && self.get_y() > center.y - 3.0 * lane_width {
-                            if car.get_y() > self.get_y() - 2.0 * car_width - 1.0 * lane_width // look ahead n number of lanes
-                            && car.get_y() < self.get_y() - 0.6 * car_width  // front of car
-                            && car.get_x() > self.get_x() - 0.6 * lane_width  // left hand side
-                            && car.get_x() < self.get_x() + 0.7 * lane_width  // right hand side
-                            && is_not_self
-                            && (car.get_current_direction() == Direction::West || car.get_current_direction() == Direction::East)
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-                    }
-                    Lane::Middle => {
-                        //Self is on the intersection but hasn't crossed middle
-                        if self.get_y() < center.y + 3.0 * lane_width && self.get_y() > center.y {
-                            if car.get_y() > self.get_y() - 2.0 * car_width - 1.0 * lane_width // look ahead n number of lanes
-                            && car.get_y() < self.get_y() - 0.6 * car_width  // front of car
-                            && car.get_x() > self.get_x() - 0.6 * lane_width  // left hand side
-                            && car.get_x() < self.get_x() + 0.7 * lane_width  // right hand side
-                            && is_not_self
-                            && (car.get_current_direction() == Direction::West || car.get_current_direction() == Direction::East)
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-                        // crossed the middle of intersection
-                        if self.get_y() < center.y && self.get_y() > center.y - 3.0 * lane_width {
-                            if car.get_y() > self.get_y() - 2.0 * car_width - 1.0 * lane_width // look ahead n number of lanes
-                            && car.get_y() < self.get_y() - 0.6 * car_width  // front of car
-                            && car.get_x() > self.get_x() - 0.6 * lane_width  // left hand side
-                            && car.get_x() < self.get_x() + 0.7 * lane_width  // right hand side
-                            && is_not_self
-                            && (car.get_current_direction() == Direction::West || car.get_current_direction() == Direction::East)
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-                    }
-                    Lane::Right => {
-                        if car.get_y() > self.get_y() - 1.0 * lane_width
-                        && car.get_y() < self.get_y() - 0.6 * car_width  // front of car
-                        && car.get_x() > self.get_x() - 0.5 * lane_width  // left hand side
-                        && car.get_x() < self.get_x() + 0.5 * lane_width  // right hand side
-                        && is_not_self
-                        // && car.get_current_direction() != Direction::North
-                        {
-                            self.set_target_speed(0.2);
-                            return false;
-                        }
-                    }
-                },
+            if self.shares_axis_with(car) || !car.reaches_into(look_ahead.area, car_width) {
+                continue;
+            }
 
-                Direction::South => match self.lane {
-                    Lane::Left => {
-                        if self.get_y() < center.y && self.get_y() > center.y - 3.0 * lane_width {
-                            if car.get_y() < self.get_y() + 2.0 * car_width + 2.0 * lane_width // look ahead n number of lanes
-                            && car.get_y() > self.get_y() + 0.6 * car_width  // front of car
-                            && car.get_x() < self.get_x() + 0.6 * lane_width  // left hand side
-                            && car.get_x() > self.get_x() - 0.6 * lane_width  // right hand side
-                            && is_not_self
-                            && (car.get_current_direction() == Direction::West || car.get_current_direction() == Direction::East)
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-                        // crossed the middle of intersection
-                        if self.get_y() > center.y && self.get_y() < center.y + 3.0 * lane_width {
-                            if car.get_y() > self.get_y() - 1.0 * lane_width // look ahead n number of lanes
-                            && car.get_y() > self.get_y() + 0.6 * car_width  // front of car
-                            && car.get_x() < self.get_x() + 0.6 * lane_width  // left hand side
-                            && car.get_x() > self.get_x() - 0.6 * lane_width  // right hand side
-                            && is_not_self
-                            && (car.get_current_direction() == Direction::West || car.get_current_direction() == Direction::East)
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-                    }
-                    Lane::Middle => {
-                        // Self is on the intersection but hasn't crossed middle
-                        if self.get_y() < center.y && self.get_y() > center.y - 3.0 * lane_width {
-                            if car.get_y() < self.get_y() + 2.0 * car_width + 2.0 * lane_width // look ahead n number of lanes
-                            && car.get_y() > self.get_y() + 0.6 * car_width  // front of car
-                            && car.get_x() < self.get_x() + 0.6 * lane_width  // left hand side
-                            && car.get_x() > self.get_x() - 0.6 * lane_width  // right hand side
-                            && is_not_self
-                            && (car.get_current_direction() == Direction::West || car.get_current_direction() == Direction::East)
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-                        // crossed the middle of intersection
-                        if self.get_y() > center.y && self.get_y() < center.y + 3.0 * lane_width {
-                            if car.get_y() > self.get_y() - 2.0 * car_width - 1.0 * lane_width // look ahead n number of lanes
-                            && car.get_y() > self.get_y() + 0.6 * car_width  // front of car
-                            && car.get_x() < self.get_x() + 0.6 * lane_width  // left hand side
-                            && car.get_x() > self.get_x() - 0.6 * lane_width  // right hand side
-                            && is_not_self
-                            && (car.get_current_direction() == Direction::West || car.get_current_direction() == Direction::East)
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-                    }
-                    Lane::Right => {
-                        if car.get_y() < self.get_y() + 1.2 * lane_width
-                        && car.get_y() > self.get_y() + 0.5 * car_width // front of car
-                        && car.get_x() < self.get_x() + 0.5 * lane_width // left hand side
-                        && car.get_x() > self.get_x() - 0.7 * car_width // right hand side
-                        && is_not_self
-                        // && car.get_current_direction() != Direction::South
-                        {
-                            self.set_target_speed(0.2);
-                            return false;
-                        }
-                    }
-                },
+            // The intersection manager keeps the conflicting movements out of the way of a car
+            // that crosses on its grant
+            if granted && uses_v2i(car) && conflicts(movement, (car.direction_from, car.lane)) {
+                continue;
+            }
 
-                Direction::West => match self.lane {
-                    Lane::Left => {
-                        if self.get_x() < center.x + 3.0 * lane_width && self.get_x() > center.x {
-                            if car.get_x() > self.get_x() - 3.0 * lane_width
-                            && car.get_x() < self.get_x() - 0.6 * car_width
-                            && car.get_y() < self.get_y() + 0.6 * lane_width // left hand
-                            && car.get_y() > self.get_y() - 0.6 * lane_width // right hand
-                            && is_not_self
-                            && car.current_direction != Direction::West
-                            && car.current_direction != Direction::East
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-
-                        if self.get_x() < center.x && self.get_x() > center.x - 3.0 * lane_width {
-                            if car.get_x() > self.get_x() - 1.0 * lane_width
-                            && car.get_x() < self.get_x() - 0.6 * car_width
-                            && car.get_y() < self.get_y() + 0.6 * lane_width // left hand
-                            && car.get_y() > self.get_y() - 0.7 * lane_width // right hand
-                            && is_not_self
-                            && car.current_direction != Direction::West
-                            && car.current_direction != Direction::East
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-                    }
-                    Lane::Middle => {
-                        if self.get_x() < center.x + 3.0 * lane_width && self.get_x() > center.x {
-                            if car.get_x() > self.get_x() - 1.2 * lane_width
-                            && car.get_x() < self.get_x() - 0.6 * car_width
-                            && car.get_y() < self.get_y() + 0.7 * lane_width // Left hand aka down
-                            && car.get_y() > self.get_y() - 0.7 * lane_width // Right hand aka up
-                            && is_not_self
-                            && (car.get_current_direction() == Direction::North
-                            || car.get_current_direction() == Direction::South)
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-
-                        if self.get_x() > center.x - 3.0 * lane_width && self.get_x() < center.x {
-                            if car.get_x() > self.get_x() - 1.2 * lane_width
-                            && car.get_x() < self.get_x() - 0.6 * car_width
-                            && car.get_y() < self.get_y() + 0.7 * lane_width // Left hand aka down
-                            && car.get_y() > self.get_y() - 0.8 * lane_width // Right hand aka up
-                            && is_not_self
-                            && (car.get_current_direction() == Direction::North
-                            || car.get_current_direction() == Direction::South)
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-                    }
-                    Lane::Right => {
-                        if car.get_x() > self.get_x() - 1.0 * lane_width
-                            && car.get_x() < self.get_x() - 0.5 * car_width
-                            && car.get_y() < self.get_y() + 0.5 * lane_width // left hand
-                            && car.get_y() > self.get_y() - 0.5 * lane_width // right hand
-                            && is_not_self
-                        {
-                            return false;
-                        }
-                    }
-                },
-                Direction::East => match self.lane {
-                    Lane::Left => {
-                        // before crossing middle of intersection
-                        if self.get_x() > center.x - 3.0 * lane_width && self.get_x() < center.x {
-                            if car.get_x() < self.get_x() + 2.5 * lane_width
-                                && car.get_x() > self.get_x() + 0.6 * car_width // front of car
-                                && car.get_y() > self.get_y() - 0.6 * lane_width // left hand
-                                && car.get_y() < self.get_y() + 0.7 * lane_width // right hand
-                                && is_not_self
-                            && car.current_direction != Direction::East
-                            && car.current_direction != Direction::West
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-
-                        // after crossing middle of intersection
-                        if self.get_x() > center.x && self.get_x() < center.x + 3.0 * lane_width {
-                            if car.get_x() < self.get_x() + 1.2 * lane_width
-                            && car.get_x() > self.get_x() + 0.6 * car_width
-                            && car.get_y() > self.get_y() - 0.6 * lane_width // left hand
-                            && car.get_y() < self.get_y() + 0.6 * lane_width // right hand
-                            && is_not_self
-                            && car.current_direction != Direction::East
-                            && car.current_direction != Direction::West
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-                    }
-                    Lane::Middle => {
-                        // before crossing middle of intersection
-                        if self.get_x() > center.x - 3.0 * lane_width && self.get_x() < center.x {
-                            if car.get_x() < self.get_x() + 3.0 * lane_width
-                            && car.get_x() > self.get_x() + 0.6 * car_width // front of car
-                            && car.get_y() > self.get_y() - 0.6 * lane_width // left hand
-                            && car.get_y() < self.get_y() + 0.6 * lane_width // right hand
-                            && is_not_self
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-
-                        // // after crossing middle of intersection
-                        if self.get_x() > center.x && self.get_x() < center.x + 3.0 * lane_width {
-                            if car.get_x() < self.get_x() + 1.5 * lane_width
-                            && car.get_x() > self.get_x() + 0.6 * car_width // front of car
-                            && car.get_y() > self.get_y() - 0.6 * lane_width // left hand
-                            && car.get_y() < self.get_y() + 0.7 * lane_width // right hand
-                            && is_not_self
-                            {
-                                if !(car.get_speed() == 0.0
-                                    && self.narrow_crossing_lane_is_clear(global_state))
-                                {
-                                    return false;
-                                }
-                            }
-                        }
-                    }
-                    Lane::Right => {
-                        if car.get_x() < self.get_x() + 1.0 * lane_width
-                            && car.get_x() > self.get_x() + 0.5 * car_width
-                            && car.get_y() > self.get_y() - 0.5 * lane_width // left hand
-                            && car.get_y() < self.get_y() + 0.5 * lane_width // right hand
-                            && is_not_self
-                        {
-                            return false;
-                        }
+            if look_ahead.crossing_only && !car.is_crossing(self) {
+                continue;
+            }
+
+            // Yielding cars only squeeze past stopped cars that leave the space right ahead free,
+            // a car with priority past the car it got priority over once its path is free
+            let can_squeeze_past = car.get_speed() == 0.0
+                && (look_ahead.yielding && self.narrow_crossing_lane_is_clear(global_state)
+                    || self.priority == Some(car.id) && self.path_ahead_is_free(global_state));
+
+            if !can_squeeze_past {
+                return Err(StopReason::CrossingTraffic(car.id, car.lane));
+            }
+        }
+        return Ok(());
+    }
+
+    /// Whether the vehicle fits into its new direction when it turns: the lane it turns into
+    /// has room for it and no vehicle coming along the new road is about to drive into it.
+    /// `StopReason::CarAhead` or `StopReason::CrossingTraffic` otherwise.
+    pub fn turn_is_clear(&self, global_state: &GlobalState) -> Result<(), StopReason> {
+        let dimensions = global_state.get_intersection().get_dimensions();
+        let turn_point = match self.get_turn_point(global_state) {
+            Some(turn_point) if matches!(self.lane, Lane::Left | Lane::Right) => turn_point,
+            _ => return Ok(()),
+        };
+
+        let distance = self.get_turn_progress() - self.get_progress(dimensions.get_center());
+        if distance > dimensions.get_lane_width() + self.get_stopping_distance() {
+            return Ok(());
+        }
+
+        let mut turned = self.turned_at(turn_point);
+        turned.same_lane_is_clear_of(self.get_perceived_cars(global_state), global_state)?;
+
+        // Vehicles driving along the road it turns into, in any of its lanes
+        let footprint = turned.get_footprint();
+        match self.get_perceived_cars(global_state).find(|car| {
+            car.current_direction == turned.current_direction
+                && car.get_reach(dimensions).overlaps(&footprint)
+        }) {
+            Some(car) => Err(StopReason::CrossingTraffic(car.id, car.lane)),
+            None => Ok(()),
+        }
+    }
+
+    /// Last check before the vehicle drives on: the space it covers until it could stop, turn
+    /// included, stays `PATH_MARGIN` clear of every other vehicle. Judged from where the
+    /// vehicles really are, so that no two footprints ever overlap. Of two vehicles heading into
+    /// each other's way the one further into the intersection goes first.
+    pub fn path_is_clear(&self, global_state: &GlobalState) -> Result<(), StopReason> {
+        let dimensions = global_state.get_intersection().get_dimensions();
+        let path = self.get_path(self.get_stopping_distance() + PATH_MARGIN, global_state);
+
+        for car in global_state.get_cars().iter().filter(|car| car.id != self.id) {
+            let must_stop = match Self::runs_into(&path, car, global_state) {
+                None => false,
+                Some(true) => true,
+                // The other vehicle only needs the space to stop, fine if it stops for self
+                Some(false) => {
+                    let other_path =
+                        car.get_path(car.get_stopping_distance() + PATH_MARGIN, global_state);
+                    match Self::runs_into(&other_path, self, global_state) {
+                        Some(true) => false,
+                        Some(false) => !self.goes_before(car, dimensions),
+                        None => true,
                     }
-                },
+                }
+            };
+
+            if !must_stop {
+                continue;
+            }
+            if car.current_direction == self.current_direction && car.lane == self.lane {
+                return Err(StopReason::CarAhead(car.id, car.lane));
+            }
+            return Err(StopReason::CrossingTraffic(car.id, car.lane));
+        }
+        Ok(())
+    }
+
+    /// The vehicle at the start of each straight part of the next `distance` metres it drives,
+    /// turning at its turn point like `move_cars` does, with the length of that part
+    fn get_path(&self, distance: f32, global_state: &GlobalState) -> Vec<(Car, f32)> {
+        let center = global_state
+            .get_intersection()
+            .get_dimensions()
+            .get_center();
+        let mut car = *self;
+        let mut distance = distance;
+        let mut path = Vec::new();
+
+        if let Some(turn_point) = self.get_turn_point(global_state) {
+            let to_turn = self.get_distance_to_turn(center);
+            if to_turn <= distance {
+                // It only turns on the frame after it has driven past the turn point
+                path.push((car, (to_turn + self.speed / 60.0).min(distance)));
+                car = self.turned_at(turn_point);
+                distance -= to_turn;
+            }
+        }
+        path.push((car, distance));
+        path
+    }
+
+    /// The space the vehicle still drives through until it has left the intersection, turn
+    /// included
+    pub fn get_way_through(&self, global_state: &GlobalState) -> Vec<Rect> {
+        let dimensions = global_state.get_intersection().get_dimensions();
+        let center = dimensions.get_center();
+        let half_width = dimensions.get_intersection_width() / 2.0;
+        let half_length = self.vehicle_type.get_length() / 2.0;
+        let to_edge = |car: &Car| (half_width - car.get_progress(center) - half_length).max(0.0);
+
+        match self.get_turn_point(global_state) {
+            Some(turn_point) => {
+                let turned = self.turned_at(turn_point);
+                vec![
+                    self.get_space_ahead(self.get_distance_to_turn(center)),
+                    turned.get_space_ahead(to_edge(&turned)),
+                ]
+            }
+            None => vec![self.get_space_ahead(to_edge(self))],
+        }
+    }
+
+    /// The vehicle as it is once it has turned at `turn_point`, see `get_turn_point`
+    pub fn turned_at(&self, turn_point: Vec2) -> Car {
+        let mut turned = *self;
+        turned.change_direction();
+        turned.leaving_intersection = true;
+        turned.x = turn_point.x;
+        turned.y = turn_point.y;
+        turned
+    }
 
+    /// Whether driving along `path` runs into `other`: `Some(true)` into where it is, or where it
+    /// would stop when it drives ahead along the path, `Some(false)` only into the space it
+    /// needs to stop.
+    fn runs_into(path: &[(Car, f32)], other: &Car, global_state: &GlobalState) -> Option<bool> {
+        let dimensions = global_state.get_intersection().get_dimensions();
+        let center = dimensions.get_center();
+        let car_width = dimensions.get_car_width();
+        let other_path = other.get_path(other.get_stopping_distance(), global_state);
+        let mut runs_into = None;
+
+        for (car, distance) in path {
+            let space = car.get_space_ahead(*distance);
+            let lateral = match car.current_direction {
+                Direction::North | Direction::South => (other.x - car.x).abs(),
+                Direction::West | Direction::East => (other.y - car.y).abs(),
                 _ => unreachable!(),
+            };
+
+            if other.current_direction == car.current_direction
+                && lateral < car_width / 2.0
+                && other.get_progress(center) > car.get_progress(center)
+            {
+                // Driving ahead, it is only in the way where it would come to a stop
+                let (stopped, stopping_distance) = other_path[other_path.len() - 1];
+                if overlaps(space, stopped.moved(stopping_distance).get_footprint()) {
+                    return Some(true);
+                }
+            } else if overlaps(space, other.get_footprint()) {
+                return Some(true);
+            } else if other_path
+                .iter()
+                .any(|(other, distance)| overlaps(space, other.get_space_ahead(*distance)))
+            {
+                runs_into = Some(false);
+            }
+        }
+        runs_into
+    }
+
+    /// Which of two vehicles heading into each other's way goes first: the one further into the
+    /// intersection, the one with the lower ID if that's a tie
+    fn goes_before(&self, other: &Car, dimensions: Dimensions) -> bool {
+        let distance = self.get_distance_to_intersection(dimensions);
+        let other_distance = other.get_distance_to_intersection(dimensions);
+        distance < other_distance || distance == other_distance && self.id < other.id
+    }
+
+    /// The vehicle moved forward by `distance`
+    fn moved(&self, distance: f32) -> Car {
+        let mut car = *self;
+        match self.current_direction {
+            Direction::North => car.y -= distance,
+            Direction::South => car.y += distance,
+            Direction::West => car.x -= distance,
+            Direction::East => car.x += distance,
+            _ => unreachable!(),
+        }
+        car
+    }
+
+    /// The area in front of the car that `crossing_lane_is_clear` checks for its current
+    /// position. Left and middle lanes look further ahead before they cross the middle of the
+    /// intersection than after.
+    pub fn get_look_ahead(&self, dimensions: Dimensions) -> Option<LookAhead> {
+        let car_width = dimensions.get_car_width();
+        let lane_width = dimensions.get_lane_width();
+
+        // Measured from the front of a regular car, like `area_ahead`
+        let extra_length = (self.vehicle_type.get_length() - 2.0 * car_width) / 2.0;
+        let progress = self.get_progress(dimensions.get_center()) + extra_length;
+        let before_middle = progress > -3.0 * lane_width && progress < 0.0;
+        let after_middle = progress > 0.0 && progress < 3.0 * lane_width;
+
+        // (front, look ahead, left hand side, right hand side, only crossing cars, yielding)
+        let (from, to, left, right, crossing_only, yielding) =
+            match (self.current_direction, self.lane) {
+                (Direction::North, Lane::Left) if before_middle => (
+                    0.6 * car_width,
+                    2.0 * car_width + 1.2 * lane_width,
+                    0.6 * lane_width,
+                    0.7 * lane_width,
+                    true,
+                    true,
+                ),
+                (Direction::North, Lane::Left | Lane::Middle) if before_middle || after_middle => (
+                    0.6 * car_width,
+                    2.0 * car_width + 1.0 * lane_width,
+                    0.6 * lane_width,
+                    0.7 * lane_width,
+                    true,
+                    true,
+                ),
+                (Direction::North, Lane::Right) => (
+                    0.6 * car_width,
+                    1.0 * lane_width,
+                    0.5 * lane_width,
+                    0.5 * lane_width,
+                    false,
+                    false,
+                ),
+
+                (Direction::South, Lane::Left | Lane::Middle) if before_middle => (
+                    0.6 * car_width,
+                    2.0 * car_width + 2.0 * lane_width,
+                    0.6 * lane_width,
+                    0.6 * lane_width,
+                    true,
+                    true,
+                ),
+                // No limit ahead once past the middle, the area runs to the end of the road
+                (Direction::South, Lane::Left | Lane::Middle) if after_middle => (
+                    0.6 * car_width,
+                    2.0 * dimensions.get_y_max(),
+                    0.6 * lane_width,
+                    0.6 * lane_width,
+                    true,
+                    true,
+                ),
+                (Direction::South, Lane::Right) => (
+                    0.5 * car_width,
+                    1.2 * lane_width,
+                    0.5 * lane_width,
+                    0.7 * car_width,
+                    false,
+                    false,
+                ),
+
+                (Direction::West, Lane::Left) if before_middle => (
+                    0.6 * car_width,
+                    3.0 * lane_width,
+                    0.6 * lane_width,
+                    0.6 * lane_width,
+                    true,
+                    true,
+                ),
+                (Direction::West, Lane::Left) if after_middle => (
+                    0.6 * car_width,
+                    1.0 * lane_width,
+                    0.6 * lane_width,
+                    0.7 * lane_width,
+                    true,
+                    true,
+                ),
+                (Direction::West, Lane::Middle) if before_middle => (
+                    0.6 * car_width,
+                    1.2 * lane_width,
+                    0.7 * lane_width,
+                    0.7 * lane_width,
+                    true,
+                    true,
+                ),
+                (Direction::West, Lane::Middle) if after_middle => (
+                    0.6 * car_width,
+                    1.2 * lane_width,
+                    0.7 * lane_width,
+                    0.8 * lane_width,
+                    true,
+                    true,
+                ),
+                (Direction::West, Lane::Right) => (
+                    0.5 * car_width,
+                    1.0 * lane_width,
+                    0.5 * lane_width,
+                    0.5 * lane_width,
+                    false,
+                    false,
+                ),
+
+                (Direction::East, Lane::Left) if before_middle => (
+                    0.6 * car_width,
+                    2.5 * lane_width,
+                    0.6 * lane_width,
+                    0.7 * lane_width,
+                    true,
+                    true,
+                ),
+                (Direction::East, Lane::Left) if after_middle => (
+                    0.6 * car_width,
+                    1.2 * lane_width,
+                    0.6 * lane_width,
+                    0.6 * lane_width,
+                    true,
+                    true,
+                ),
+                (Direction::East, Lane::Middle) if before_middle => (
+                    0.6 * car_width,
+                    3.0 * lane_width,
+                    0.6 * lane_width,
+                    0.6 * lane_width,
+                    false,
+                    true,
+                ),
+                (Direction::East, Lane::Middle) if after_middle => (
+                    0.6 * car_width,
+                    1.5 * lane_width,
+                    0.6 * lane_width,
+                    0.7 * lane_width,
+                    false,
+                    true,
+                ),
+                (Direction::East, Lane::Right) => (
+                    0.5 * car_width,
+                    1.0 * lane_width,
+                    0.5 * lane_width,
+                    0.5 * lane_width,
+                    false,
+                    false,
+                ),
+
+                // Cyclists only ride straight through, next to the intersection
+                (_, Lane::Bike) => (
+                    0.5 * car_width,
+                    1.0 * lane_width,
+                    0.4 * lane_width,
+                    0.4 * lane_width,
+                    true,
+                    false,
+                ),
+
+                _ => return None,
+            };
+
+        // Cautious drivers want a bigger gap to the crossing traffic, aggressive ones a smaller
+        let to = from + (to - from) * self.driver.get_gap_acceptance();
+
+        Some(LookAhead {
+            area: self.area_ahead(from, to, left, right, car_width),
+            crossing_only,
+            yielding,
+        })
+    }
+
+    pub fn narrow_crossing_lane_is_clear(&self, global_state: &GlobalState) -> bool {
+        let dimensions = global_state.get_intersection().get_dimensions();
+        let car_width = dimensions.get_car_width();
+        let cars = self.get_perceived_cars(global_state);
+        let area = self.get_narrow_area(dimensions);
+
+        for car in cars {
+            if !self.shares_axis_with(car) && car.reaches_into(area, car_width) {
+                return false;
             }
         }
         return true;
     }
 
-    pub fn narrow_crossing_lane_is_clear(&mut self, global_state: &GlobalState) -> bool {
-        let direction = self.get_current_direction();
+    /// Whether no vehicle stands within a lane width in front of the vehicle, judged from where
+    /// the vehicles really are rather than where the driver sees them. The vehicles ahead in its
+    /// lane are left to `same_lane_is_clear`.
+    fn path_ahead_is_free(&self, global_state: &GlobalState) -> bool {
+        let dimensions = global_state.get_intersection().get_dimensions();
+        let area = self.get_space_ahead(dimensions.get_lane_width());
+
+        global_state.get_cars().iter().all(|car| {
+            car.id == self.id || self.shares_axis_with(car) || !car.get_footprint().overlaps(&area)
+        })
+    }
+
+    /// Whether `car` is exactly on the same x or y axis as self, like the cars ahead and behind
+    /// in its lane. The crossing checks leave those to `same_lane_is_clear`.
+    fn shares_axis_with(&self, car: &Car) -> bool {
+        car.x == self.x || car.y == self.y
+    }
+
+    /// The other cars as the driver currently sees them, `Driver::get_perception_delay` seconds
+    /// ago and through the sensors when they are enabled
+    pub fn get_perceived_cars<'a>(
+        &self,
+        global_state: &'a GlobalState,
+    ) -> impl Iterator<Item = &'a Car> + Clone + 'a {
+        let id = self.id;
+        let cars = match global_state.get_perception(id) {
+            Some(cars) => cars,
+            None => global_state.get_perceived_cars(self.driver.get_perception_delay()),
+        };
+        cars.iter().filter(move |car| car.id != id)
+    }
+
+    /// Whether no pedestrian is crossing right in front of the car. Cars only meet crossing
+    /// pedestrians when they turn out of the intersection at the end of a vehicle phase.
+    pub fn crosswalk_is_clear(&self, global_state: &GlobalState) -> Result<(), StopReason> {
         let dimensions = global_state.get_intersection().get_dimensions();
         let car_width = dimensions.get_car_width();
         let lane_width = dimensions.get_lane_width();
-        let cars = global_state.get_cars();
+        let area = self.area_ahead(
+            0.0,
+            car_width + 0.5 * lane_width,
+            1.5 * lane_width,
+            1.5 * lane_width,
+            car_width,
+        );
 
-        for car in cars {
-            let is_not_self = car.get_y() != self.get_y() && car.get_x() != self.get_x();
-            match direction {
-                Direction::South => {
-                    if car.get_y() < self.get_y() + 1.8 * lane_width
-                        && car.get_y() > self.get_y() + 0.5 * car_width
-                        && car.get_x() > self.get_x() - 1.3 * car_width
-                        && car.get_x() < self.get_x() + 1.3 * car_width
-                        && is_not_self
-                    {
-                        return false;
-                    }
-                }
-                Direction::West => {
-                    if car.get_x() > self.get_x() - 1.5 * lane_width
-                        && car.get_x() < self.get_x() - 0.5 * car_width
-                        && car.get_y() > self.get_y() - 1.3 * car_width
-                        && car.get_y() < self.get_y() + 1.3 * car_width
-                        && is_not_self
-                    {
-                        return false;
-                    }
-                }
-                Direction::North => {
-                    if car.get_y() > self.get_y() - 1.4 * lane_width
-                        && car.get_y() < self.get_y() - 0.5 * car_width
-                        && car.get_x() > self.get_x() - 1.3 * car_width
-                        && car.get_x() < self.get_x() + 1.3 * car_width
-                        && is_not_self
-                    {
-                        return false;
-                    }
-                }
-                Direction::East => {
-                    if car.get_x() < self.get_x() + 1.6 * lane_width
-                        && car.get_x() > self.get_x() + 0.5 * car_width
-                        && car.get_y() > self.get_y() - 1.3 * car_width
-                        && car.get_y() < self.get_y() + 1.3 * car_width
-                        && is_not_self
-                    {
-                        return false;
-                    }
+        if global_state.get_pedestrians().iter().any(|pedestrian| {
+            pedestrian.get_state() == PedestrianState::Crossing
+                && area.contains(pedestrian.get_position())
+        }) {
+            return Err(StopReason::Pedestrian);
+        }
+        Ok(())
+    }
+
+    /// Whether the car can go on without cutting off a cyclist. Cars yield to the cyclists in a
+    /// bike lane they are about to drive across, and cars turning right also yield to the
+    /// cyclists riding up next to them (right hook).
+    pub fn cyclists_are_clear(&self, global_state: &GlobalState) -> Result<(), StopReason> {
+        if self.vehicle_type == VehicleType::Bicycle {
+            return Ok(());
+        }
+
+        let intersection = global_state.get_intersection();
+        let dimensions = intersection.get_dimensions();
+        let center = dimensions.get_center();
+        let car_width = dimensions.get_car_width();
+        let lane_width = dimensions.get_lane_width();
+        let cyclists = self
+            .get_perceived_cars(global_state)
+            .filter(|car| car.vehicle_type == VehicleType::Bicycle);
+
+        // Once on a bike lane keep going to clear it
+        let footprint = self.get_footprint();
+        let on_bike_lane = [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ]
+        .iter()
+        .any(|direction| {
+            Self::is_perpendicular(*direction, self.current_direction)
+                && footprint.overlaps(&intersection.get_bike_lane(*direction))
+        });
+
+        let area = self.area_ahead(
+            0.0,
+            car_width + 0.75 * lane_width,
+            1.0 * lane_width,
+            1.0 * lane_width,
+            car_width,
+        );
+        let progress = self.get_progress(center);
+        let turn_progress = self.get_turn_progress();
+        let turning_right = self.lane == Lane::Right
+            && !self.leaving_intersection
+            && turn_progress - progress < 1.5 * lane_width;
+
+        // Cars with priority squeeze past a stopped cyclist like past a stopped car
+        let can_squeeze_past = |cyclist: &Car| {
+            self.priority == Some(cyclist.id)
+                && cyclist.speed == 0.0
+                && self.path_ahead_is_free(global_state)
+        };
+
+        for cyclist in cyclists {
+            if can_squeeze_past(cyclist) {
+                continue;
+            }
+
+            if !on_bike_lane && cyclist.is_crossing(self) && cyclist.reaches_into(area, car_width) {
+                return Err(StopReason::Cyclist(cyclist.id));
+            }
+
+            if turning_right && cyclist.current_direction == self.current_direction {
+                let cyclist_progress = cyclist.get_progress(center);
+
+                // Cyclists waiting behind the car let it turn first
+                let waiting_behind = cyclist.speed == 0.0 && cyclist_progress < progress;
+
+                if !waiting_behind
+                    && cyclist_progress > progress - 2.0 * lane_width
+                    && cyclist_progress < turn_progress + 0.5 * lane_width
+                {
+                    return Err(StopReason::Cyclist(cyclist.id));
                 }
+            }
+        }
+        Ok(())
+    }
 
-                _ => unreachable!(),
+    /// The area right in front of the car checked by `narrow_crossing_lane_is_clear`.
+    pub fn get_narrow_area(&self, dimensions: Dimensions) -> Rect {
+        let car_width = dimensions.get_car_width();
+        let lane_width = dimensions.get_lane_width();
+
+        let look_ahead = match self.current_direction {
+            Direction::South => 1.8,
+            Direction::West => 1.5,
+            Direction::North => 1.4,
+            Direction::East => 1.6,
+            _ => unreachable!(),
+        };
+
+        self.area_ahead(
+            0.5 * car_width,
+            look_ahead * lane_width,
+            1.3 * car_width,
+            1.3 * car_width,
+            car_width,
+        )
+    }
+
+    /// Rectangle from `from` to `to` in front of the car's center and `left`/`right` to its
+    /// sides. The distances are tuned for a vehicle of 1 x 2 car widths, so the rectangle is
+    /// pushed forward and widened by however much bigger the vehicle is, and stretched by the
+    /// distance the vehicle needs to stop.
+    fn area_ahead(&self, from: f32, to: f32, left: f32, right: f32, car_width: f32) -> Rect {
+        let extra_length = (self.vehicle_type.get_length() - 2.0 * car_width) / 2.0;
+        let extra_width = (self.vehicle_type.get_width() - car_width) / 2.0;
+        let from = from + extra_length;
+        let to = to + extra_length + self.get_stopping_distance();
+        let left = left + extra_width;
+        let right = right + extra_width;
+
+        match self.current_direction {
+            Direction::North => Rect::new(self.x - left, self.y - to, left + right, to - from),
+            Direction::South => Rect::new(self.x - right, self.y + from, left + right, to - from),
+            Direction::West => Rect::new(self.x - to, self.y - right, to - from, left + right),
+            Direction::East => Rect::new(self.x + from, self.y - left, to - from, left + right),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Whether the vehicle reaches into `area`. Areas are tuned for the center point of a
+    /// vehicle of 1 x 2 car widths, so they grow or shrink by however much bigger or smaller the
+    /// vehicle is.
+    pub fn reaches_into(&self, area: Rect, car_width: f32) -> bool {
+        let extent = self.get_extent();
+        let car_extent = match self.current_direction {
+            Direction::North | Direction::South => Vec2::new(0.5 * car_width, car_width),
+            Direction::West | Direction::East => Vec2::new(car_width, 0.5 * car_width),
+            _ => unreachable!(),
+        };
+        let grow = extent - car_extent;
+
+        Rect::new(
+            area.x - grow.x,
+            area.y - grow.y,
+            (area.w + 2.0 * grow.x).max(0.0),
+            (area.h + 2.0 * grow.y).max(0.0),
+        )
+        .contains(Vec2::new(self.x, self.y))
+    }
+
+    /// Distance the car has travelled past the middle of the intersection, negative while it is
+    /// still approaching it.
+    pub fn get_progress(&self, center: Point) -> f32 {
+        match self.current_direction {
+            Direction::North => center.y - self.y,
+            Direction::South => self.y - center.y,
+            Direction::West => center.x - self.x,
+            Direction::East => self.x - center.x,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Progress, see `get_progress`, at which the car turns into its new direction
+    pub fn get_turn_progress(&self) -> f32 {
+        match self.direction_from {
+            Direction::North | Direction::West => -self.turn_offset,
+            Direction::South | Direction::East => self.turn_offset,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Distance the car still drives before it turns, see `get_turn_point`
+    fn get_distance_to_turn(&self, center: Point) -> f32 {
+        (self.get_turn_progress() - self.get_progress(center)).max(0.0)
+    }
+
+    /// Distance from the front of the car to the edge of the intersection. Negative once the
+    /// car has entered the intersection.
+    pub fn get_distance_to_intersection(&self, dimensions: Dimensions) -> f32 {
+        if self.is_leaving_intersection() {
+            return f32::NEG_INFINITY;
+        }
+
+        let half_length = self.vehicle_type.get_length() / 2.0;
+        -dimensions.get_intersection_width() / 2.0
+            - (self.get_progress(dimensions.get_center()) + half_length)
+    }
+
+    /// Whether the vehicle drives across the path of `other`
+    pub fn is_crossing(&self, other: &Car) -> bool {
+        Self::is_perpendicular(self.current_direction, other.current_direction)
+    }
+
+    fn is_perpendicular(a: Direction, b: Direction) -> bool {
+        match a {
+            Direction::North | Direction::South => b == Direction::West || b == Direction::East,
+            Direction::West | Direction::East => b == Direction::North || b == Direction::South,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Half of the vehicle's size along the x and y axis.
+    pub fn get_extent(&self) -> Vec2 {
+        let half_length = self.vehicle_type.get_length() / 2.0;
+        let half_width = self.vehicle_type.get_width() / 2.0;
+
+        match self.current_direction {
+            Direction::North | Direction::South => Vec2::new(half_width, half_length),
+            Direction::West | Direction::East => Vec2::new(half_length, half_width),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn get_footprint(&self) -> Rect {
+        let extent = self.get_extent();
+        Rect::new(
+            self.x - extent.x,
+            self.y - extent.y,
+            2.0 * extent.x,
+            2.0 * extent.y,
+        )
+    }
+
+    /// Footprint stretched forward over a lane width and the stopping distance while the
+    /// vehicle moves, the space it may still cover before it could stop for something.
+    pub fn get_reach(&self, dimensions: Dimensions) -> Rect {
+        if self.speed == 0.0 {
+            return self.get_footprint();
+        }
+
+        self.get_space_ahead(dimensions.get_lane_width() + self.get_stopping_distance())
+    }
+
+    /// Footprint stretched forward by `distance`
+    fn get_space_ahead(&self, distance: f32) -> Rect {
+        let footprint = self.get_footprint();
+        match self.current_direction {
+            Direction::North => Rect::new(
+                footprint.x,
+                footprint.y - distance,
+                footprint.w,
+                footprint.h + distance,
+            ),
+            Direction::South => {
+                Rect::new(footprint.x, footprint.y, footprint.w, footprint.h + distance)
+            }
+            Direction::West => Rect::new(
+                footprint.x - distance,
+                footprint.y,
+                footprint.w + distance,
+                footprint.h,
+            ),
+            Direction::East => {
+                Rect::new(footprint.x, footprint.y, footprint.w + distance, footprint.h)
             }
+            _ => unreachable!(),
         }
-        return true;
+    }
+
+    fn get_braking_distance(speed: f32) -> f32 {
+        speed * speed / (2.0 * BRAKING)
+    }
+
+    /// Distance the car needs to brake to a stop from its current speed
+    pub fn get_stopping_distance(&self) -> f32 {
+        Self::get_braking_distance(self.speed)
+    }
+
+    /// Distance between the centers of two vehicles in the same lane on top of the safe
+    /// distance
+    fn get_clearance(&self, other: &Car, car_width: f32) -> f32 {
+        (self.vehicle_type.get_length() + other.vehicle_type.get_length()) / 2.0 - car_width
+    }
+
+    /// Whether the car is held up by the intersection: on its way in while it is held or within
+    /// `CONTROL_DISTANCE` of the stop line, and until it has left the intersection again. Only
+    /// pulling away, like after spawning at the edge of the world, doesn't count as held.
+    fn is_in_control_area(&self, global_state: &GlobalState) -> bool {
+        let dimensions = global_state.get_intersection().get_dimensions();
+
+        if self.leaving_intersection {
+            return self.is_at_intersection(global_state);
+        }
+
+        matches!(self.stop_reason, Some(reason) if reason != StopReason::Reacting)
+            || get_distance_to_stop_line(self, dimensions) <= CONTROL_DISTANCE
     }
 
     pub fn is_at_intersection(self, global_state: &GlobalState) -> bool {
@@ -675,49 +1218,50 @@ impl Car {
         let center = dimensions.get_center();
         let intersection_width = dimensions.get_intersection_width();
         let car_width = dimensions.get_car_width();
+        let half_length = self.vehicle_type.get_length() / 2.0;
 
         match self.current_direction {
             Direction::South => {
-                if self.y < center.y + intersection_width / 2.0 - car_width
+                if self.y < center.y + intersection_width / 2.0 - half_length
                     && self.is_leaving_intersection()
                 {
                     return true;
-                } else if self.y > center.y - intersection_width / 2.0 - 2.0 * car_width
+                } else if self.y > center.y - intersection_width / 2.0 - half_length - car_width
                     && !self.is_leaving_intersection()
                 {
                     return true;
                 }
             }
             Direction::West => {
-                if self.x > center.x - intersection_width / 2.0 + car_width
+                if self.x > center.x - intersection_width / 2.0 + half_length
                     && self.is_leaving_intersection()
                 {
                     return true;
-                } else if self.x < center.x + intersection_width / 2.0 + 2.0 * car_width
+                } else if self.x < center.x + intersection_width / 2.0 + half_length + car_width
                     && !self.is_leaving_intersection()
                 {
                     return true;
                 }
             }
             Direction::North => {
-                if self.y > center.y - intersection_width / 2.0 + car_width
+                if self.y > center.y - intersection_width / 2.0 + half_length
                     && self.is_leaving_intersection()
                 {
                     return true;
-                } else if self.y < center.y + intersection_width / 2.0 + 2.0 * car_width
+                } else if self.y < center.y + intersection_width / 2.0 + half_length + car_width
                     && !self.is_leaving_intersection()
                 {
                     return true;
                 }
             }
             Direction::East => {
-                if self.x < center.x + intersection_width / 2.0 - car_width
+                if self.x < center.x + intersection_width / 2.0 - half_length
                     && self.is_leaving_intersection()
                 {
                     return true;
                 }
 
-                if self.x > center.x - intersection_width / 2.0 - 2.0 * car_width
+                if self.x > center.x - intersection_width / 2.0 - half_length - car_width
                     && !self.is_leaving_intersection()
                 {
                     return true;
@@ -731,28 +1275,23 @@ impl Car {
     }
 
     pub fn draw(&self, global_state: &GlobalState) {
-        let car_width = global_state
-            .get_intersection()
-            .get_dimensions()
-            .get_car_width();
+        let width = self.vehicle_type.get_width();
+        let length = self.vehicle_type.get_length();
+
+        if self.vehicle_type != VehicleType::Car {
+            self.draw_shape(width, length);
+            return;
+        }
 
         draw_texture_ex(
             global_state.get_car_sprite(),
-            match self.current_direction {
-                Direction::North | Direction::South => self.x - car_width / 2.0,
-                Direction::West | Direction::East => self.x - 0.5 * car_width,
-                _ => unreachable!(),
-            },
-            match self.current_direction {
-                Direction::North | Direction::South => self.y - 1.0 * car_width,
-                Direction::West | Direction::East => self.y - car_width,
-                _ => unreachable!(),
-            },
+            self.x - width / 2.0,
+            self.y - length / 2.0,
             Color::new(1.0, 1.0, 1.0, 1.0),
             DrawTextureParams {
                 dest_size: Some(Vec2 {
-                    x: car_width,
-                    y: car_width * 2.0,
+                    x: width,
+                    y: length,
                 }),
                 source: Some(Rect {
                     x: self.sprite.0,
@@ -768,33 +1307,143 @@ impl Car {
         );
     }
 
-    pub fn change_direction(&mut self) {
-        let lane = self.get_lane();
-        self.set_current_direction(match self.get_current_direction() {
-            Direction::North => match lane {
-                Lane::Left => Direction::West,
-                Lane::Middle => Direction::North,
-                Lane::Right => Direction::East,
-            },
+    /// Label with the reason the car is stopping and an arrow to the vehicle blocking it
+    pub fn draw_stop_reason(&self, cars: &Cars, global_state: &GlobalState) {
+        let reason = match self.stop_reason {
+            Some(reason) => reason,
+            None => return,
+        };
+        let dimensions = global_state.get_intersection().get_dimensions();
+        let car_width = dimensions.get_car_width();
+        let line_width = dimensions.get_line_width();
+        let from = Vec2::new(self.x, self.y);
 
-            Direction::South => match lane {
-                Lane::Left => Direction::East,
-                Lane::Middle => Direction::South,
-                Lane::Right => Direction::West,
+        if let Some(blocker) = reason
+            .get_blocker()
+            .and_then(|id| cars.iter().find(|car| car.id == id))
+        {
+            let to = Vec2::new(blocker.x, blocker.y);
+            let direction = (to - from).normalize_or_zero();
+            let normal = Vec2::new(-direction.y, direction.x);
+            let head = car_width / 2.0;
+
+            draw_line(from.x, from.y, to.x, to.y, 2.0 * line_width, ORANGE);
+            draw_triangle(
+                to,
+                to - direction * head + normal * head / 2.0,
+                to - direction * head - normal * head / 2.0,
+                ORANGE,
+            );
+        }
+
+        draw_text_ex(
+            &reason.get_description(),
+            self.x + car_width,
+            self.y,
+            TextParams {
+                font: global_state.get_text_font(),
+                // Rendered at a readable size and scaled down to the size of the world
+                font_size: 32,
+                font_scale: car_width * 0.8 / 32.0,
+                font_scale_aspect: 1.0,
+                color: ORANGE,
+                rotation: 0.0,
             },
+        );
+    }
 
-            Direction::West => match lane {
-                Lane::Left => Direction::South,
-                Lane::Middle => Direction::West,
-                Lane::Right => Direction::North,
+    /// Debug overlay: safety envelope, look-ahead areas, turn point and heading of the car
+    pub fn draw_detection_zones(&self, global_state: &GlobalState) {
+        let dimensions = global_state.get_intersection().get_dimensions();
+        let car_width = dimensions.get_car_width();
+        let line_width = dimensions.get_line_width();
+        let draw_area = |area: Rect, color: Color| {
+            draw_rectangle_lines(area.x, area.y, area.w, area.h, line_width, color)
+        };
+
+        draw_area(self.get_safety_envelope(global_state), GREEN);
+
+        if let Some(look_ahead) = self.get_look_ahead(dimensions) {
+            draw_area(look_ahead.area, YELLOW);
+            if look_ahead.yielding {
+                draw_area(self.get_narrow_area(dimensions), SKYBLUE);
+            }
+        }
+
+        if let Some(point) = self.get_turn_point(global_state) {
+            draw_circle_lines(point.x, point.y, car_width / 4.0, line_width, MAGENTA);
+        }
+
+        let heading = Vec2::new(self.rotation.sin(), -self.rotation.cos()) * 2.0 * car_width;
+        draw_line(
+            self.x,
+            self.y,
+            self.x + heading.x,
+            self.y + heading.y,
+            2.0 * line_width,
+            WHITE,
+        );
+    }
+
+    /// Draws the vehicles that have no sprite as a body with a darker cabin in the front.
+    fn draw_shape(&self, width: f32, length: f32) {
+        let color = self.vehicle_type.get_color();
+        let cabin_length = match self.vehicle_type {
+            VehicleType::Truck => 0.25 * length,
+            VehicleType::Bus => 0.1 * length,
+            VehicleType::Motorcycle => 0.4 * length,
+            VehicleType::Emergency => 0.3 * length,
+            VehicleType::Bicycle => 0.4 * length,
+            VehicleType::Car => 0.3 * length,
+        };
+
+        draw_rectangle_ex(
+            self.x,
+            self.y,
+            width,
+            length,
+            DrawRectangleParams {
+                offset: Vec2::new(0.5, 0.5),
+                rotation: self.rotation,
+                color,
             },
-            Direction::East => match lane {
-                Lane::Left => Direction::North,
-                Lane::Middle => Direction::East,
-                Lane::Right => Direction::South,
+        );
+
+        draw_rectangle_ex(
+            self.x,
+            self.y,
+            0.8 * width,
+            cabin_length,
+            DrawRectangleParams {
+                offset: Vec2::new(0.5, length / (2.0 * cabin_length)),
+                rotation: self.rotation,
+                color: Color::new(color.r * 0.5, color.g * 0.5, color.b * 0.5, 1.0),
             },
-            _ => unreachable!(),
-        });
+        );
+
+        if self.vehicle_type == VehicleType::Emergency {
+            // Siren right behind the cabin, flashing red and blue
+            let siren_length = 0.1 * length;
+            draw_rectangle_ex(
+                self.x,
+                self.y,
+                width,
+                siren_length,
+                DrawRectangleParams {
+                    offset: Vec2::new(0.5, (length / 2.0 - cabin_length) / siren_length),
+                    rotation: self.rotation,
+                    color: if (self.driving_time * 4.0) as i32 % 2 == 0 {
+                        RED
+                    } else {
+                        BLUE
+                    },
+                },
+            );
+        }
+    }
+
+    pub fn change_direction(&mut self) {
+        self.set_current_direction(self.get_current_direction().turn(self.get_lane()));
     }
 
     //setters
@@ -827,11 +1476,35 @@ impl Car {
         self.current_direction = new_value;
     }
 
+    pub fn set_permission(&mut self, new_value: Permission) {
+        self.permission = new_value;
+    }
+
+    pub fn set_message_time(&mut self, new_value: f32) {
+        self.message_time = new_value;
+    }
+
+    pub fn set_commanded_speed(&mut self, new_value: Option<f32>) {
+        self.commanded_speed = new_value;
+    }
+
+    pub fn set_priority(&mut self, new_value: Option<u64>) {
+        self.priority = new_value;
+    }
+
+    pub fn set_commanded_permission(&mut self, new_value: Option<bool>) {
+        self.commanded_permission = new_value;
+    }
+
     pub fn set_driving_time(&mut self, new_value: f32) {
         self.driving_time = new_value;
     }
 
     //getters
+    pub fn get_id(&self) -> u64 {
+        self.id
+    }
+
     pub fn get_x(&self) -> f32 {
         self.x
     }
@@ -860,6 +1533,10 @@ impl Car {
         self.current_direction
     }
 
+    pub fn get_direction_from(&self) -> Direction {
+        self.direction_from
+    }
+
     pub fn get_lane(&self) -> Lane {
         self.lane
     }
@@ -871,9 +1548,71 @@ impl Car {
     pub fn get_driving_time(&self) -> f32 {
         self.driving_time
     }
+
+    pub fn get_vehicle_type(&self) -> VehicleType {
+        self.vehicle_type
+    }
+
+    pub fn get_permission(&self) -> Permission {
+        self.permission
+    }
+
+    pub fn get_message_time(&self) -> f32 {
+        self.message_time
+    }
+
+    pub fn get_commanded_speed(&self) -> Option<f32> {
+        self.commanded_speed
+    }
+
+    pub fn get_commanded_permission(&self) -> Option<bool> {
+        self.commanded_permission
+    }
+
+    pub fn get_stop_reason(&self) -> Option<StopReason> {
+        self.stop_reason
+    }
+
+    pub fn get_priority(&self) -> Option<u64> {
+        self.priority
+    }
+
+    pub fn get_driver(&self) -> Driver {
+        self.driver
+    }
+
+    /// Time lost compared to driving through at free flow speed
+    pub fn get_delay(&self) -> f32 {
+        self.delay
+    }
+
+    /// Highway Capacity Manual control delay: the time lost slowing down, queueing and driving
+    /// through the intersection, without the time lost elsewhere on the way
+    pub fn get_control_delay(&self) -> f32 {
+        self.control_delay
+    }
+
+    pub fn get_stops(&self) -> u32 {
+        self.stops
+    }
+}
+
+/// Whether two areas share more than an edge
+pub fn overlaps(a: Rect, b: Rect) -> bool {
+    a.left() < b.right() && b.left() < a.right() && a.top() < b.bottom() && b.top() < a.bottom()
 }
 
+/// Area checked for crossing traffic, see `Car::get_look_ahead`
 #[derive(Debug, Clone, PartialEq, Copy)]
+pub struct LookAhead {
+    pub area: Rect,
+    /// Only cars driving across this car's path block it
+    pub crossing_only: bool,
+    /// A stopped car doesn't block as long as the narrow area in front is clear
+    pub yielding: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
 pub enum Direction {
     North,
     South,
@@ -882,9 +1621,154 @@ pub enum Direction {
     Random,
 }
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+impl Direction {
+    /// Every direction a vehicle can come from, in the order used by `get_index`
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    pub fn get_index(&self) -> usize {
+        match self {
+            Direction::North => 0,
+            Direction::East => 1,
+            Direction::South => 2,
+            Direction::West => 3,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Direction a vehicle heading this way drives in after turning from `lane`
+    pub fn turn(self, lane: Lane) -> Direction {
+        match self {
+            Direction::North => match lane {
+                Lane::Left => Direction::West,
+                Lane::Middle | Lane::Bike => Direction::North,
+                Lane::Right => Direction::East,
+            },
+
+            Direction::South => match lane {
+                Lane::Left => Direction::East,
+                Lane::Middle | Lane::Bike => Direction::South,
+                Lane::Right => Direction::West,
+            },
+
+            Direction::West => match lane {
+                Lane::Left => Direction::South,
+                Lane::Middle | Lane::Bike => Direction::West,
+                Lane::Right => Direction::North,
+            },
+            Direction::East => match lane {
+                Lane::Left => Direction::North,
+                Lane::Middle | Lane::Bike => Direction::East,
+                Lane::Right => Direction::South,
+            },
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
 pub enum Lane {
     Left,
     Middle,
     Right,
+    /// Bike lane on the right hand side of the road, only used by cyclists
+    Bike,
+}
+
+impl Lane {
+    /// Every lane, in the order used by `get_index`
+    pub const ALL: [Lane; 4] = [Lane::Left, Lane::Middle, Lane::Right, Lane::Bike];
+
+    pub fn get_index(&self) -> usize {
+        match self {
+            Lane::Left => 0,
+            Lane::Middle => 1,
+            Lane::Right => 2,
+            Lane::Bike => 3,
+        }
+    }
+
+    /// Movement through the intersection made from this lane
+    pub fn get_movement_name(&self) -> &'static str {
+        match self {
+            Lane::Left => "Left Turn",
+            Lane::Middle => "Straight",
+            Lane::Right => "Right Turn",
+            Lane::Bike => "Bike Lane",
+        }
+    }
+}
+
+/// What makes a car stop, see `Car::get_stop_reason`
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+pub enum StopReason {
+    /// ID and lane of the car in front
+    CarAhead(u64, Lane),
+    /// ID and lane of the car in the way through the intersection
+    CrossingTraffic(u64, Lane),
+    /// ID of the emergency vehicle that has priority
+    EmergencyVehicle(u64),
+    /// Red light while the pedestrians have their walk phase
+    WalkPhase,
+    /// Waiting for a reservation from the intersection manager
+    NoGrant,
+    /// Held by the external controller
+    Controller,
+    Pedestrian,
+    /// ID of the cyclist the car yields to
+    Cyclist(u64),
+    /// Driver hasn't reacted to the way being clear yet
+    Reacting,
+}
+
+impl StopReason {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            StopReason::CarAhead(..) => "Car ahead",
+            StopReason::CrossingTraffic(..) => "Crossing traffic",
+            StopReason::EmergencyVehicle(_) => "Emergency vehicle",
+            StopReason::WalkPhase => "Red light",
+            StopReason::NoGrant => "No reservation",
+            StopReason::Controller => "Controller",
+            StopReason::Pedestrian => "Pedestrian",
+            StopReason::Cyclist(_) => "Cyclist",
+            StopReason::Reacting => "Reacting",
+        }
+    }
+
+    /// ID of the vehicle causing the stop, if it is one
+    pub fn get_blocker(&self) -> Option<u64> {
+        match self {
+            StopReason::CarAhead(id, _)
+            | StopReason::CrossingTraffic(id, _)
+            | StopReason::EmergencyVehicle(id)
+            | StopReason::Cyclist(id) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Whether the car could drive on but gives way to another vehicle
+    pub fn yields_to_vehicle(&self) -> bool {
+        matches!(self, StopReason::CrossingTraffic(..) | StopReason::Cyclist(_))
+    }
+
+    pub fn get_description(&self) -> String {
+        match self {
+            StopReason::CarAhead(id, lane) => format!("Blocked by car #{id} in {lane:?} lane"),
+            StopReason::CrossingTraffic(id, lane) => {
+                format!("Crossing car #{id} in {lane:?} lane")
+            }
+            StopReason::EmergencyVehicle(id) => format!("Yielding to emergency vehicle #{id}"),
+            StopReason::WalkPhase => String::from("Red light, pedestrians walking"),
+            StopReason::NoGrant => String::from("Waiting for reservation"),
+            StopReason::Controller => String::from("Held by controller"),
+            StopReason::Pedestrian => String::from("Pedestrian crossing"),
+            StopReason::Cyclist(id) => format!("Yielding to cyclist #{id}"),
+            StopReason::Reacting => String::from("Reacting"),
+        }
+    }
 }
@@ -0,0 +1,120 @@
+use macroquad::prelude::{Rect, Vec2};
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+
+use super::cars::Cars;
+use super::Car;
+use crate::intersection::dimensions::Dimensions;
+use crate::random;
+
+/// Imperfect view drivers get of the other vehicles, see `sense`
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SensorConfig {
+    /// Drivers see every vehicle exactly when disabled
+    pub enabled: bool,
+    /// How far the driver sees, in lane widths
+    pub range: f32,
+    /// Angle in degrees around the heading of the vehicle that the driver sees
+    pub field_of_view: f32,
+    /// Whether vehicles hide the vehicles behind them
+    pub occlusion: bool,
+    /// Standard deviation of the seen positions, in car widths
+    pub position_noise: f32,
+    /// Standard deviation of the seen speeds, in metres per second
+    pub speed_noise: f32,
+}
+
+impl Default for SensorConfig {
+    fn default() -> Self {
+        SensorConfig {
+            enabled: false,
+            range: 12.0,
+            field_of_view: 220.0,
+            occlusion: true,
+            position_noise: 0.1,
+            speed_noise: 0.25,
+        }
+    }
+}
+
+/// The vehicles in `cars` that the driver of `car` can see, with noise on their position and
+/// speed.
+pub fn sense(car: &Car, cars: Cars, config: &SensorConfig, dimensions: Dimensions) -> Cars {
+    let car_width = dimensions.get_car_width();
+    let position = Vec2::new(car.get_x(), car.get_y());
+    let heading = Vec2::new(car.get_rotation().sin(), -car.get_rotation().cos());
+    let range = config.range * dimensions.get_lane_width();
+    let half_view = (config.field_of_view / 2.0).to_radians().cos();
+
+    let in_view: Cars = cars
+        .into_iter()
+        .filter(|other| {
+            let offset = Vec2::new(other.get_x(), other.get_y()) - position;
+            let distance = offset.length();
+
+            // Vehicles right next to the driver are always noticed
+            if distance < car.get_vehicle_type().get_length() {
+                return true;
+            }
+            distance <= range && heading.dot(offset / distance) >= half_view
+        })
+        .collect();
+
+    let mut seen = Cars::new();
+    for other in in_view.iter() {
+        let target = Vec2::new(other.get_x(), other.get_y());
+
+        if config.occlusion
+            && in_view.iter().any(|blocker| {
+                blocker.get_id() != other.get_id()
+                    && position.distance(Vec2::new(blocker.get_x(), blocker.get_y()))
+                        < position.distance(target)
+                    && blocks_line(blocker.get_footprint(), position, target)
+            })
+        {
+            continue;
+        }
+
+        let mut other = *other;
+        other.set_x(other.get_x() + noise(config.position_noise * car_width));
+        other.set_y(other.get_y() + noise(config.position_noise * car_width));
+        // Standing vehicles are told apart from moving ones reliably
+        if other.get_speed() > 0.0 {
+            other.set_speed((other.get_speed() + noise(config.speed_noise)).max(0.0));
+        }
+        seen.push(other);
+    }
+    seen
+}
+
+fn noise(std_dev: f32) -> f32 {
+    match Normal::new(0.0, std_dev) {
+        Ok(normal) => normal.sample(&mut random::rng()),
+        Err(_) => 0.0,
+    }
+}
+
+/// Whether the line of sight from `from` to `to` passes through `area`
+fn blocks_line(area: Rect, from: Vec2, to: Vec2) -> bool {
+    let direction = to - from;
+    let mut enter: f32 = 0.0;
+    let mut exit: f32 = 1.0;
+
+    for (start, delta, min, max) in [
+        (from.x, direction.x, area.left(), area.right()),
+        (from.y, direction.y, area.top(), area.bottom()),
+    ] {
+        if delta == 0.0 {
+            if start < min || start > max {
+                return false;
+            }
+            continue;
+        }
+
+        let (a, b) = ((min - start) / delta, (max - start) / delta);
+        enter = enter.max(a.min(b));
+        exit = exit.min(a.max(b));
+    }
+    enter <= exit
+}
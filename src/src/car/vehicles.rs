@@ -0,0 +1,158 @@
+use macroquad::prelude::{Color, BEIGE, DARKBLUE, DARKGREEN, MAROON, ORANGE, WHITE};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::VEHICLE_TYPE_WEIGHTS;
+use crate::random;
+
+/// Vehicle classes that can be spawned into the intersection.
+///
+/// Sizes are in metres, speeds in metres per second and accelerations in metres per second
+/// squared.
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+pub enum VehicleType {
+    Car,
+    Truck,
+    Bus,
+    Motorcycle,
+    /// Ambulance that gets absolute priority at the intersection
+    Emergency,
+    /// Cyclist riding straight through in the bike lane
+    Bicycle,
+}
+
+impl VehicleType {
+    /// Every spawnable vehicle class, in the order used by `get_index`.
+    pub const ALL: [VehicleType; 6] = [
+        VehicleType::Car,
+        VehicleType::Truck,
+        VehicleType::Bus,
+        VehicleType::Motorcycle,
+        VehicleType::Emergency,
+        VehicleType::Bicycle,
+    ];
+
+    /// Vehicle class drawn from `VEHICLE_TYPE_WEIGHTS`. Cyclists only ride in the bike lane, so
+    /// they are left out of the draw.
+    pub fn select_random() -> VehicleType {
+        let mut rng = random::rng();
+        let weights = || {
+            Self::ALL
+                .into_iter()
+                .zip(VEHICLE_TYPE_WEIGHTS)
+                .filter(|(vehicle_type, _)| *vehicle_type != VehicleType::Bicycle)
+        };
+        let total: u32 = weights().map(|(_, weight)| weight).sum();
+        let mut roll = rng.gen_range(0..total);
+
+        for (vehicle_type, weight) in weights() {
+            if roll < weight {
+                return vehicle_type;
+            }
+            roll -= weight;
+        }
+        unreachable!()
+    }
+
+    pub fn get_index(&self) -> usize {
+        match self {
+            VehicleType::Car => 0,
+            VehicleType::Truck => 1,
+            VehicleType::Bus => 2,
+            VehicleType::Motorcycle => 3,
+            VehicleType::Emergency => 4,
+            VehicleType::Bicycle => 5,
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            VehicleType::Car => "Cars",
+            VehicleType::Truck => "Trucks",
+            VehicleType::Bus => "Buses",
+            VehicleType::Motorcycle => "Motorcycles",
+            VehicleType::Emergency => "Emergency",
+            VehicleType::Bicycle => "Cyclists",
+        }
+    }
+
+    pub fn get_length(&self) -> f32 {
+        match self {
+            VehicleType::Car => 4.5,
+            VehicleType::Truck => 8.0,
+            VehicleType::Bus => 10.5,
+            VehicleType::Motorcycle => 2.2,
+            VehicleType::Emergency => 5.5,
+            VehicleType::Bicycle => 1.8,
+        }
+    }
+
+    pub fn get_width(&self) -> f32 {
+        match self {
+            VehicleType::Car => 1.8,
+            VehicleType::Truck => 2.5,
+            VehicleType::Bus => 2.55,
+            VehicleType::Motorcycle => 0.8,
+            VehicleType::Emergency => 2.0,
+            VehicleType::Bicycle => 0.6,
+        }
+    }
+
+    pub fn get_max_speed(&self) -> f32 {
+        match self {
+            VehicleType::Car => 11.5,
+            VehicleType::Truck => 8.5,
+            VehicleType::Bus => 9.5,
+            VehicleType::Motorcycle => 13.5,
+            VehicleType::Emergency => 14.5,
+            VehicleType::Bicycle => 4.5,
+        }
+    }
+
+    pub fn get_acceleration(&self) -> f32 {
+        match self {
+            VehicleType::Car => 3.0,
+            VehicleType::Truck => 1.2,
+            VehicleType::Bus => 1.2,
+            VehicleType::Motorcycle => 4.5,
+            VehicleType::Emergency => 3.0,
+            VehicleType::Bicycle => 1.5,
+        }
+    }
+
+    pub fn get_turning_radius(&self) -> f32 {
+        match self {
+            VehicleType::Car => 5.5,
+            VehicleType::Truck => 9.0,
+            VehicleType::Bus => 11.0,
+            VehicleType::Motorcycle => 3.0,
+            VehicleType::Emergency => 6.0,
+            VehicleType::Bicycle => 2.0,
+        }
+    }
+
+    /// Speed used inside the intersection. Vehicles with a wider turning radius than a car
+    /// have to slow down more to make a turn.
+    pub fn get_intersection_speed(&self, turning: bool) -> f32 {
+        let speed = 6.5_f32.min(self.get_max_speed());
+
+        if !turning {
+            return speed;
+        }
+
+        (speed * (VehicleType::Car.get_turning_radius() / self.get_turning_radius()).sqrt())
+            .min(speed)
+    }
+
+    /// Body color for the vehicles that are drawn with shapes instead of a sprite.
+    pub fn get_color(&self) -> Color {
+        match self {
+            VehicleType::Truck => MAROON,
+            VehicleType::Bus => ORANGE,
+            VehicleType::Motorcycle => DARKBLUE,
+            VehicleType::Emergency => WHITE,
+            VehicleType::Bicycle => DARKGREEN,
+            VehicleType::Car => BEIGE,
+        }
+    }
+}
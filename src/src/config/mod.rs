@@ -2,6 +2,22 @@ use macroquad::{
     miniquad::conf::{LinuxBackend, LinuxX11Gl, Platform},
     window::Conf,
 };
+use rand_distr::{Distribution as _, Normal};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::car::deadlocks::DeadlockConfig;
+use crate::car::drivers::DriverConfig;
+use crate::car::sensors::SensorConfig;
+use crate::constants::CONFIG_FILE;
+use crate::controller::ControllerConfig;
+use crate::gym::GymConfig;
+use crate::invariants::StrictConfig;
+use crate::pedestrian::PedestrianConfig;
+use crate::random;
+use crate::statistics::safety::SafetyConfig;
+use crate::telemetry::TelemetryConfig;
+use crate::v2i::V2iConfig;
 
 /// Initializes the costum config for macroquad to use
 pub fn init_config() -> Conf {
@@ -22,3 +38,82 @@ pub fn init_config() -> Conf {
         icon: None,
     }
 }
+
+/// Settings of the simulation, read from `CONFIG_FILE` in the working directory when it exists.
+/// Fields missing from the file keep their default value.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub drivers: DriverConfig,
+    pub sensors: SensorConfig,
+    pub v2i: V2iConfig,
+    pub controller: ControllerConfig,
+    pub telemetry: TelemetryConfig,
+    pub pedestrians: PedestrianConfig,
+    pub gym: GymConfig,
+    pub safety: SafetyConfig,
+    pub strict: StrictConfig,
+    pub deadlocks: DeadlockConfig,
+}
+
+impl Config {
+    pub fn load() -> Config {
+        let contents = match fs::read_to_string(CONFIG_FILE) {
+            Ok(contents) => contents,
+            Err(_) => return Config::default(),
+        };
+
+        match serde_json::from_str::<Config>(&contents) {
+            Ok(mut config) => {
+                config.validate();
+                config
+            }
+            Err(err) => {
+                eprintln!("Ignoring {CONFIG_FILE}: {err}");
+                Config::default()
+            }
+        }
+    }
+
+    /// Replaces the settings the simulation can't run with by their default, with a warning
+    fn validate(&mut self) {
+        self.drivers.validate();
+        self.telemetry.validate();
+        self.deadlocks.validate();
+        self.gym.validate();
+    }
+}
+
+/// Normal distribution clamped to `min..=max`. The default always samples 0.0.
+#[derive(Debug, Default, Clone, PartialEq, Copy, Serialize, Deserialize)]
+pub struct Distribution {
+    pub mean: f32,
+    pub std_dev: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Distribution {
+    pub fn new(mean: f32, std_dev: f32, min: f32, max: f32) -> Self {
+        Distribution {
+            mean,
+            std_dev,
+            min,
+            max,
+        }
+    }
+
+    /// Whether the distribution can be sampled: `min` isn't above `max` and `std_dev` isn't
+    /// negative
+    pub fn is_valid(&self) -> bool {
+        self.min <= self.max && self.std_dev >= 0.0
+    }
+
+    pub fn sample(&self) -> f32 {
+        let value = match Normal::new(self.mean, self.std_dev) {
+            Ok(normal) => normal.sample(&mut random::rng()),
+            Err(_) => self.mean,
+        };
+        value.clamp(self.min, self.max)
+    }
+}
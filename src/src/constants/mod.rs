@@ -136,3 +136,88 @@ pub const SPRITE_CARS: [(f32, f32, f32, f32); 21] = [
         12.0 * TILE_SIDE,
     ),
 ];
+
+// VEHICLES
+
+/// Spawn weights for `VehicleType::ALL` (car, truck, bus, motorcycle, emergency, bicycle).
+/// Emergency vehicles and cyclists are only spawned on demand.
+pub const VEHICLE_TYPE_WEIGHTS: [u32; 6] = [14, 2, 2, 2, 0, 0];
+
+/// Deceleration of every vehicle when braking, in metres per second squared
+pub const BRAKING: f32 = 6.0;
+
+/// Metres every vehicle keeps free in front of it on top of its braking distance, see
+/// `Car::path_is_clear`
+pub const PATH_MARGIN: f32 = 0.5;
+
+/// How many lane widths away from the intersection an emergency vehicle starts holding
+/// conflicting traffic.
+pub const PREEMPTION_DISTANCE: f32 = 8.0;
+
+// PEDESTRIANS
+
+/// Minimum seconds the cars drive before the pedestrians get a walk phase.
+pub const MIN_VEHICLE_PHASE: f32 = 10.0;
+
+/// Seconds pedestrians may start crossing during a walk phase.
+pub const WALK_PHASE: f32 = 6.0;
+
+// CONFIG
+
+/// Optional JSON file with the simulation settings, see `Config`.
+pub const CONFIG_FILE: &str = "config.json";
+
+// DRIVERS
+
+/// Longest perception delay in seconds, also how far back the positions of the cars are kept.
+pub const MAX_PERCEPTION_DELAY: f32 = 2.0;
+
+// STATISTICS
+
+/// Gap in car widths below which two vehicles count as a close call.
+pub const CLOSE_CALL_MARGIN: f32 = 0.25;
+
+/// Speed in metres per second below which a vehicle that hasn't entered the intersection yet
+/// counts as queued.
+pub const QUEUE_SPEED: f32 = 0.5;
+
+/// Distance before the stop line in metres within which vehicles slow down for the
+/// intersection, the delay from there on counts as control delay.
+pub const CONTROL_DISTANCE: f32 = 20.0;
+
+/// Seconds of history shown in the charts.
+pub const CHART_SECONDS: usize = 300;
+
+/// Bars of the travel time histogram, the last one takes every longer trip too.
+pub const TRAVEL_TIME_BINS: usize = 12;
+
+/// Seconds of travel time each bar of the histogram covers.
+pub const TRAVEL_TIME_BIN: f32 = 10.0;
+
+/// Side of a cell of the heatmap over the intersection box in metres.
+pub const HEATMAP_CELL: f32 = 0.5;
+
+/// Seconds of history shown in the time-space diagrams.
+pub const TRAJECTORY_SECONDS: f32 = 60.0;
+
+/// Seconds of history shown in the vehicle trails.
+pub const TRAIL_SECONDS: f32 = 5.0;
+
+// WORLD
+
+/// Width of a lane in metres, every other size of the intersection follows from it.
+pub const LANE_WIDTH: f32 = 3.5;
+
+/// Kilometres per hour in a metre per second, speeds are only shown in km/h.
+pub const KMH_PER_MS: f32 = 3.6;
+
+// CAMERA
+
+/// Zoom factor of a single mouse wheel step.
+pub const ZOOM_STEP: f32 = 1.1;
+
+/// Smallest zoom, showing twice the window.
+pub const MIN_ZOOM: f32 = 0.5;
+
+/// Largest zoom.
+pub const MAX_ZOOM: f32 = 10.0;
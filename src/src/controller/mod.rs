@@ -0,0 +1,355 @@
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::time::Duration;
+
+use crate::car::{vehicles::VehicleType, Car, Direction, Lane};
+use crate::intersection::signals::SignalPhase;
+use crate::state::GlobalState;
+use crate::statistics::{deserialize_kmh, serialize_kmh};
+use crate::v2i::Permission;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControllerConfig {
+    /// Listens for an external controller when enabled
+    pub enabled: bool,
+    pub address: String,
+    /// Waits every tick until the controller has answered with its commands
+    pub lock_step: bool,
+    /// Seconds the simulation waits for the answer in lock-step before the cars drive on their
+    /// own for the tick
+    pub timeout: f32,
+}
+
+impl Default for ControllerConfig {
+    fn default() -> Self {
+        ControllerConfig {
+            enabled: false,
+            address: String::from("127.0.0.1:7878"),
+            lock_step: false,
+            timeout: 1.0,
+        }
+    }
+}
+
+/// World state published to the controller every tick, one JSON object per line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldState {
+    pub tick: u64,
+    pub time: f32,
+    pub signal_phase: SignalPhase,
+    pub cars: Vec<CarState>,
+}
+
+/// State of a single car, positions in metres and speeds in metres per second. Speeds are
+/// exported in km/h like in the statistics.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CarState {
+    pub id: u64,
+    pub vehicle_type: VehicleType,
+    pub x: f32,
+    pub y: f32,
+    pub rotation: f32,
+    #[serde(
+        rename = "speed_kmh",
+        serialize_with = "serialize_kmh",
+        deserialize_with = "deserialize_kmh"
+    )]
+    pub speed: f32,
+    #[serde(
+        rename = "target_speed_kmh",
+        serialize_with = "serialize_kmh",
+        deserialize_with = "deserialize_kmh"
+    )]
+    pub target_speed: f32,
+    pub direction_from: Direction,
+    pub current_direction: Direction,
+    pub lane: Lane,
+    pub leaving_intersection: bool,
+    pub permission: Permission,
+}
+
+impl CarState {
+    pub fn new(car: &Car) -> Self {
+        CarState {
+            id: car.get_id(),
+            vehicle_type: car.get_vehicle_type(),
+            x: car.get_x(),
+            y: car.get_y(),
+            rotation: car.get_rotation(),
+            speed: car.get_speed(),
+            target_speed: car.get_target_speed(),
+            direction_from: car.get_direction_from(),
+            current_direction: car.get_current_direction(),
+            lane: car.get_lane(),
+            leaving_intersection: car.is_leaving_intersection(),
+            permission: car.get_permission(),
+        }
+    }
+}
+
+/// Answer of the controller to a `WorldState`, one JSON object per line
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Commands {
+    pub commands: Vec<Command>,
+}
+
+/// Overrides for a single car that stay until the next command for it. Leaving a field out
+/// hands that decision back to the car.
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+pub struct Command {
+    /// Tick of the `WorldState` the command answers
+    pub tick: u64,
+    pub car_id: u64,
+    /// Highest speed the car may drive at in metres per second, it still slows down for what is
+    /// in front of it
+    #[serde(default)]
+    pub speed: Option<f32>,
+    /// `false` holds the car at the stop line, `true` lets it in without waiting for a grant
+    #[serde(default)]
+    pub permission: Option<bool>,
+}
+
+/// TCP server an external intersection controller connects to. One controller is served at a
+/// time, the simulation keeps running on its own while none is connected.
+pub struct ControllerServer {
+    listener: TcpListener,
+    client: Option<BufReader<TcpStream>>,
+    /// Partial line read from the controller
+    pending: String,
+    /// World state the controller hasn't taken from the socket yet
+    outgoing: Vec<u8>,
+    lock_step: bool,
+    timeout: Duration,
+    tick: u64,
+}
+
+impl ControllerServer {
+    /// Starts listening when the controller is enabled in the config
+    pub fn start(config: &ControllerConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+
+        let listener = match TcpListener::bind(&config.address) {
+            Ok(listener) => listener,
+            Err(err) => {
+                eprintln!(
+                    "Controller server can't listen on {}: {err}",
+                    config.address
+                );
+                return None;
+            }
+        };
+        listener
+            .set_nonblocking(true)
+            .expect("listener can be made non-blocking");
+
+        Some(ControllerServer {
+            listener,
+            client: None,
+            pending: String::new(),
+            outgoing: Vec::new(),
+            lock_step: config.lock_step,
+            timeout: Duration::from_secs_f32(config.timeout.max(0.001)),
+            tick: 0,
+        })
+    }
+
+    /// Address the server listens on
+    pub fn get_address(&self) -> Option<SocketAddr> {
+        self.listener.local_addr().ok()
+    }
+
+    /// Publishes the world state and applies the commands the controller sent back
+    pub fn step(&mut self, global_state: &mut GlobalState) {
+        self.tick += 1;
+        self.accept();
+
+        if self.client.is_none() {
+            return;
+        }
+
+        // A controller that reads slower than the simulation runs skips the states it can't
+        // keep up with
+        if self.outgoing.is_empty() {
+            let state = WorldState {
+                tick: self.tick,
+                time: global_state.get_statistics().get_animation_time(),
+                signal_phase: global_state.get_signals().get_phase(),
+                cars: global_state.get_cars().iter().map(CarState::new).collect(),
+            };
+            let mut line = serde_json::to_string(&state).expect("world state always serializes");
+            line.push('\n');
+            self.outgoing.extend_from_slice(line.as_bytes());
+        }
+
+        if !self.flush() {
+            self.disconnect();
+            return;
+        }
+
+        if self.lock_step && !self.outgoing.is_empty() {
+            self.time_out(global_state);
+            return;
+        }
+
+        let client = match self.client.as_mut() {
+            Some(client) => client,
+            None => return,
+        };
+
+        // In lock-step the read blocks until the controller has answered this tick, or until
+        // the timeout
+        loop {
+            match client.read_line(&mut self.pending) {
+                Ok(0) => {
+                    self.disconnect();
+                    return;
+                }
+                Ok(_) if self.pending.ends_with('\n') => {
+                    let answered = match serde_json::from_str::<Commands>(&self.pending) {
+                        Ok(commands) => apply_current(
+                            commands.commands,
+                            self.tick,
+                            self.lock_step,
+                            global_state,
+                        ),
+                        Err(err) => {
+                            eprintln!("Ignoring controller commands: {err}");
+                            true
+                        }
+                    };
+                    self.pending.clear();
+
+                    if self.lock_step && answered {
+                        return;
+                    }
+                }
+                Ok(_) => {}
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if self.lock_step {
+                        self.time_out(global_state);
+                    }
+                    return;
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(_) => {
+                    self.disconnect();
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Writes as much of the outgoing world state as the controller takes, without blocking or
+    /// within the timeout in lock-step. False once the controller is gone.
+    fn flush(&mut self) -> bool {
+        let client = match self.client.as_mut() {
+            Some(client) => client,
+            None => return false,
+        };
+
+        while !self.outgoing.is_empty() {
+            match client.get_mut().write(&self.outgoing) {
+                Ok(0) => return false,
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return true
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(_) => return false,
+            }
+        }
+        true
+    }
+
+    /// A lock-step controller that doesn't answer in time hands the cars back to their drivers
+    /// for the tick, so a hung controller can't freeze the simulation
+    fn time_out(&mut self, global_state: &mut GlobalState) {
+        eprintln!(
+            "Controller didn't answer tick {} in time, the cars drive on their own",
+            self.tick
+        );
+
+        let mut cars = global_state.get_cars();
+        for car in cars.iter_mut() {
+            car.set_commanded_speed(None);
+            car.set_commanded_permission(None);
+        }
+        global_state.set_cars(cars);
+    }
+
+    fn accept(&mut self) {
+        if self.client.is_some() {
+            return;
+        }
+
+        if let Ok((stream, _)) = self.listener.accept() {
+            let configured = if self.lock_step {
+                stream
+                    .set_nonblocking(false)
+                    .and_then(|_| stream.set_read_timeout(Some(self.timeout)))
+                    .and_then(|_| stream.set_write_timeout(Some(self.timeout)))
+            } else {
+                stream.set_nonblocking(true)
+            };
+
+            if configured.is_ok() {
+                self.client = Some(BufReader::new(stream));
+                self.pending.clear();
+                self.outgoing.clear();
+            }
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.client = None;
+        self.pending.clear();
+        self.outgoing.clear();
+    }
+}
+
+/// Applies the commands for the current tick and drops the others, in lock-step the ones that
+/// answer an earlier tick too. False when all of them were dropped, the controller hasn't
+/// answered this tick yet then.
+fn apply_current(
+    commands: Vec<Command>,
+    tick: u64,
+    lock_step: bool,
+    global_state: &mut GlobalState,
+) -> bool {
+    let received = commands.len();
+    let current: Vec<Command> = commands
+        .into_iter()
+        .filter(|command| command.tick <= tick && !(lock_step && command.tick < tick))
+        .collect();
+
+    if current.len() < received {
+        eprintln!(
+            "Dropping {} controller commands that don't answer tick {tick}",
+            received - current.len()
+        );
+    }
+
+    apply_commands(&current, global_state);
+    received == 0 || !current.is_empty()
+}
+
+/// Sets the overrides of the commanded cars, commands for unknown cars are ignored
+pub fn apply_commands(commands: &[Command], global_state: &mut GlobalState) {
+    let mut cars = global_state.get_cars();
+
+    for command in commands.iter() {
+        if let Some(car) = cars.iter_mut().find(|car| car.get_id() == command.car_id) {
+            car.set_commanded_speed(command.speed);
+            car.set_commanded_permission(command.permission);
+        }
+    }
+
+    global_state.set_cars(cars);
+}
@@ -0,0 +1,327 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::car::{vehicles::VehicleType, Car, Direction, Lane};
+use crate::config::Config;
+use crate::constants::{CONFIG_FILE, QUEUE_SPEED};
+use crate::controller::{apply_commands, CarState, Command};
+use crate::random;
+use crate::state::GlobalState;
+
+/// Approaches in the order of `Action::Approaches` and the queue length observation
+pub const APPROACHES: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
+/// Lanes in the order of the queue length observation
+pub const LANES: [Lane; 4] = [Lane::Left, Lane::Middle, Lane::Right, Lane::Bike];
+
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObservationEncoding {
+    /// Waiting vehicles per approach and lane
+    QueueLengths,
+    /// State of every vehicle
+    CarList,
+    /// Share of every grid cell covered by vehicles
+    OccupancyGrid,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Observation {
+    /// `APPROACHES` x `LANES` values, row by row
+    QueueLengths(Vec<f32>),
+    CarList(Vec<CarState>),
+    /// `grid_size` x `grid_size` values, row by row from the top left
+    OccupancyGrid(Vec<f32>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Hands every decision back to the cars
+    Free,
+    /// Which of the `APPROACHES` may enter the intersection
+    Approaches([bool; 4]),
+    /// Commands per car, like the external controller sends them, their tick is ignored
+    Commands(Vec<Command>),
+}
+
+/// Weights of the reward components, the reward of a step is
+/// `throughput * finished - delay * delay seconds - safety * close calls`
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RewardWeights {
+    pub throughput: f32,
+    pub delay: f32,
+    pub safety: f32,
+}
+
+impl Default for RewardWeights {
+    fn default() -> Self {
+        RewardWeights {
+            throughput: 1.0,
+            delay: 0.1,
+            safety: 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GymConfig {
+    pub observation: ObservationEncoding,
+    /// Cells per side of the occupancy grid
+    pub grid_size: usize,
+    pub reward: RewardWeights,
+    /// Vehicles spawned per second on average, the pedestrians arrive at
+    /// `PedestrianConfig::rate` like in the window
+    pub spawn_rate: f32,
+    /// Cyclists spawned per second on average, on top of the vehicles
+    pub cyclist_rate: f32,
+    /// Simulated seconds after which an episode is done
+    pub episode_length: f32,
+    /// Frames simulated per step
+    pub frames_per_step: u32,
+}
+
+impl Default for GymConfig {
+    fn default() -> Self {
+        GymConfig {
+            observation: ObservationEncoding::QueueLengths,
+            grid_size: 32,
+            reward: RewardWeights::default(),
+            spawn_rate: 1.0,
+            cyclist_rate: 0.15,
+            episode_length: 300.0,
+            frames_per_step: 30,
+        }
+    }
+}
+
+impl GymConfig {
+    pub fn validate(&mut self) {
+        if self.grid_size == 0 {
+            eprintln!("Ignoring gym.grid_size in {CONFIG_FILE}: it isn't at least 1");
+            self.grid_size = GymConfig::default().grid_size;
+        }
+
+        if self.episode_length <= 0.0 || self.episode_length.is_nan() {
+            eprintln!(
+                "Ignoring gym.episode_length in {CONFIG_FILE}: {} isn't above 0",
+                self.episode_length
+            );
+            self.episode_length = GymConfig::default().episode_length;
+        }
+
+        if self.frames_per_step == 0 {
+            eprintln!("Ignoring gym.frames_per_step in {CONFIG_FILE}: it isn't at least 1");
+            self.frames_per_step = GymConfig::default().frames_per_step;
+        }
+    }
+}
+
+/// Headless simulation with a reinforcement learning style interface
+pub struct Environment {
+    config: Config,
+    global_state: GlobalState,
+    /// Frames simulated in the episode so far
+    frames: u32,
+}
+
+impl Environment {
+    pub fn new(config: Config) -> Self {
+        let global_state = GlobalState::headless(config.clone());
+
+        Environment {
+            config,
+            global_state,
+            frames: 0,
+        }
+    }
+
+    /// Starts a new episode, the same seed and actions give the same episode
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        random::seed(seed);
+        self.global_state = GlobalState::headless(self.config.clone());
+        self.frames = 0;
+        self.observe()
+    }
+
+    /// Applies the action, simulates `frames_per_step` frames and returns the observation, the
+    /// reward and whether the episode is done
+    pub fn step(&mut self, action: &Action) -> (Observation, f32, bool) {
+        let gym = self.config.gym.clone();
+        let before = self.global_state.get_cars();
+        let close_calls = self.global_state.get_statistics().get_close_calls();
+        let finished = self.finished();
+
+        self.apply_action(action);
+
+        for _ in 0..gym.frames_per_step {
+            self.spawn();
+            self.global_state.simulate_frame();
+            self.frames += 1;
+        }
+
+        let after = self.global_state.get_cars();
+        let finished = self.finished() - finished;
+        let delay: f32 = after
+            .iter()
+            .map(|car| {
+                let previous = before
+                    .iter()
+                    .find(|other| other.get_id() == car.get_id())
+                    .map_or(0.0, |other| other.get_delay());
+                car.get_delay() - previous
+            })
+            .sum();
+        let new_close_calls = self.global_state.get_statistics().get_close_calls() - close_calls;
+
+        let reward = gym.reward.throughput * finished
+            - gym.reward.delay * delay
+            - gym.reward.safety * new_close_calls;
+        // Counted in frames, the animation time drifts from adding up 1 / 60
+        let done = self.frames >= (gym.episode_length * 60.0).round() as u32;
+        if done {
+            // The episode ends the conflicts still going on, so they show up in its statistics
+            self.global_state.get_statistics_mut().end_safety_events();
+        }
+
+        (self.observe(), reward, done)
+    }
+
+    /// Trips finished at the edge of the world, the towed vehicles end theirs too but don't count
+    fn finished(&self) -> f32 {
+        let statistics = self.global_state.get_statistics();
+        statistics.get_finished_trips() as f32 - statistics.get_deadlocks().removed
+    }
+
+    fn apply_action(&mut self, action: &Action) {
+        match action {
+            Action::Free => {
+                let mut cars = self.global_state.get_cars();
+                for car in cars.iter_mut() {
+                    car.set_commanded_speed(None);
+                    car.set_commanded_permission(None);
+                }
+                self.global_state.set_cars(cars);
+            }
+            Action::Approaches(allowed) => {
+                let mut cars = self.global_state.get_cars();
+                for car in cars.iter_mut() {
+                    let index = APPROACHES
+                        .iter()
+                        .position(|direction| *direction == car.get_direction_from())
+                        .unwrap();
+                    car.set_commanded_permission(if car.is_leaving_intersection() {
+                        None
+                    } else {
+                        Some(allowed[index])
+                    });
+                }
+                self.global_state.set_cars(cars);
+            }
+            Action::Commands(commands) => apply_commands(commands, &mut self.global_state),
+        }
+    }
+
+    fn spawn(&mut self) {
+        let gym = &self.config.gym;
+        let mut rng = random::rng();
+
+        if rng.gen::<f32>() < gym.spawn_rate / 60.0 {
+            let car = Car::new(Direction::Random, None, &self.global_state);
+            self.global_state.spawn_car(car);
+        }
+
+        if rng.gen::<f32>() < gym.cyclist_rate / 60.0 {
+            let cyclist = Car::new(
+                Direction::Random,
+                Some(VehicleType::Bicycle),
+                &self.global_state,
+            );
+            self.global_state.spawn_car(cyclist);
+        }
+    }
+
+    pub fn observe(&self) -> Observation {
+        match self.config.gym.observation {
+            ObservationEncoding::QueueLengths => Observation::QueueLengths(self.queue_lengths()),
+            ObservationEncoding::CarList => Observation::CarList(
+                self.global_state
+                    .get_cars()
+                    .iter()
+                    .map(CarState::new)
+                    .collect(),
+            ),
+            ObservationEncoding::OccupancyGrid => Observation::OccupancyGrid(self.occupancy_grid()),
+        }
+    }
+
+    /// Vehicles that haven't entered the intersection yet and are (almost) standing still
+    fn queue_lengths(&self) -> Vec<f32> {
+        let cars = self.global_state.get_cars();
+        let mut queues = vec![0.0; APPROACHES.len() * LANES.len()];
+
+        for car in cars.iter() {
+            if car.is_leaving_intersection() || car.get_speed() >= QUEUE_SPEED {
+                continue;
+            }
+
+            let approach = APPROACHES
+                .iter()
+                .position(|direction| *direction == car.get_direction_from())
+                .unwrap();
+            let lane = LANES
+                .iter()
+                .position(|lane| *lane == car.get_lane())
+                .unwrap();
+            queues[approach * LANES.len() + lane] += 1.0;
+        }
+        queues
+    }
+
+    /// Covers the whole world around the center of the intersection, the cells are as wide and
+    /// high as the world divided by `grid_size`
+    fn occupancy_grid(&self) -> Vec<f32> {
+        let size = self.config.gym.grid_size;
+        let dimensions = self.global_state.get_intersection().get_dimensions();
+        let center = dimensions.get_center();
+        let half_width = dimensions.get_x_max();
+        let half_height = dimensions.get_y_max();
+        let cell_width = 2.0 * half_width / size as f32;
+        let cell_height = 2.0 * half_height / size as f32;
+        let mut grid = vec![0.0; size * size];
+
+        for car in self.global_state.get_cars().iter() {
+            let footprint = car.get_footprint();
+
+            for row in 0..size {
+                for column in 0..size {
+                    let left = center.x - half_width + column as f32 * cell_width;
+                    let top = center.y - half_height + row as f32 * cell_height;
+                    let right = left + cell_width;
+                    let bottom = top + cell_height;
+                    let width =
+                        (footprint.right().min(right) - footprint.left().max(left)).max(0.0);
+                    let height =
+                        (footprint.bottom().min(bottom) - footprint.top().max(top)).max(0.0);
+
+                    grid[row * size + column] += width * height / (cell_width * cell_height);
+                }
+            }
+        }
+
+        grid.iter().map(|value: &f32| value.min(1.0)).collect()
+    }
+
+    //getters
+    pub fn get_global_state(&self) -> &GlobalState {
+        &self.global_state
+    }
+}
@@ -0,0 +1,288 @@
+use crate::car::{overlaps, vehicles::VehicleType, Car, Direction, Lane, StopReason};
+use crate::constants::{MIN_VEHICLE_PHASE, PREEMPTION_DISTANCE, WALK_PHASE};
+use crate::intersection::dimensions::Dimensions;
+use crate::intersection::signals::SignalPhase;
+use crate::pedestrian::PedestrianState;
+use crate::state::GlobalState;
+use crate::v2i::{holds_grant, manager::conflicts, uses_v2i, Permission};
+use macroquad::prelude::Rect;
+
+/// Whether the intersection lets `car` drive on, the reason it has to hold at the stop line
+/// otherwise
+pub fn check_policy(car: &Car, global_state: &GlobalState) -> Result<(), StopReason> {
+    if let Some(emergency) = get_emergency_to_yield_to(car, global_state) {
+        return Err(StopReason::EmergencyVehicle(emergency.get_id()));
+    }
+    if must_stop_for_walk_phase(car, global_state) {
+        return Err(StopReason::WalkPhase);
+    }
+    if must_wait_for_grant(car, global_state) {
+        return Err(StopReason::NoGrant);
+    }
+    if must_wait_for_controller(car, global_state) {
+        return Err(StopReason::Controller);
+    }
+    if let Some(other) = get_conflicting_in_box(car, global_state) {
+        return Err(StopReason::CrossingTraffic(other.get_id(), other.get_lane()));
+    }
+    if let Some(other) = get_crossing_bike_lane(car, global_state) {
+        return Err(StopReason::CrossingTraffic(other.get_id(), other.get_lane()));
+    }
+    Ok(())
+}
+
+/// Emergency vehicle `car` has to hold before the intersection for to clear its way. Cars
+/// already inside the intersection keep going so they clear the path, as do the cars whose
+/// movement doesn't cross or merge with the emergency vehicle's.
+pub fn get_emergency_to_yield_to(car: &Car, global_state: &GlobalState) -> Option<Car> {
+    if car.get_vehicle_type() == VehicleType::Emergency {
+        return None;
+    }
+
+    let dimensions = global_state.get_intersection().get_dimensions();
+
+    if !is_held_before_intersection(car, dimensions) {
+        return None;
+    }
+
+    car.get_perceived_cars(global_state)
+        .find(|other| {
+            other.get_vehicle_type() == VehicleType::Emergency
+                && conflicts(
+                    (other.get_direction_from(), other.get_lane()),
+                    (car.get_direction_from(), car.get_lane()),
+                )
+                && has_priority(other, global_state)
+        })
+        .copied()
+}
+
+/// Whether the emergency vehicle is close enough to, or already inside, the intersection to
+/// hold the conflicting traffic.
+fn has_priority(emergency: &Car, global_state: &GlobalState) -> bool {
+    let dimensions = global_state.get_intersection().get_dimensions();
+
+    if emergency.is_leaving_intersection() {
+        return emergency.is_at_intersection(global_state);
+    }
+
+    emergency.get_distance_to_intersection(dimensions)
+        < PREEMPTION_DISTANCE * dimensions.get_lane_width()
+}
+
+/// Whether `car` has to stop at the stop line because the pedestrians have, or are about to
+/// get, their walk phase. Emergency vehicles are let through.
+pub fn must_stop_for_walk_phase(car: &Car, global_state: &GlobalState) -> bool {
+    if global_state.get_signals().get_phase() == SignalPhase::Vehicles
+        || car.get_vehicle_type() == VehicleType::Emergency
+    {
+        return false;
+    }
+
+    is_approaching_stop_line(car, global_state.get_intersection().get_dimensions())
+}
+
+/// Moves the pedestrian signals to the next phase once the current one is done. The walk phase
+/// is exclusive: it only starts once no car is left in the intersection or on a crosswalk.
+pub fn schedule_walk_phase(global_state: &mut GlobalState) {
+    let mut signals = global_state.get_signals();
+    let pedestrians = global_state.get_pedestrians();
+
+    signals.set_phase_time(signals.get_phase_time() + 1.0 / 60.0);
+
+    match signals.get_phase() {
+        SignalPhase::Vehicles => {
+            if signals.get_phase_time() >= MIN_VEHICLE_PHASE
+                && pedestrians
+                    .iter()
+                    .any(|pedestrian| pedestrian.get_state() == PedestrianState::Waiting)
+            {
+                signals.set_phase(SignalPhase::Clearance);
+            }
+        }
+        SignalPhase::Clearance => {
+            if intersection_is_clear(global_state) {
+                signals.set_phase(SignalPhase::Walk);
+            }
+        }
+        SignalPhase::Walk => {
+            if signals.get_phase_time() >= WALK_PHASE {
+                signals.set_phase(SignalPhase::Flashing);
+            }
+        }
+        SignalPhase::Flashing => {
+            if pedestrians
+                .iter()
+                .all(|pedestrian| pedestrian.get_state() != PedestrianState::Crossing)
+            {
+                signals.set_phase(SignalPhase::Vehicles);
+            }
+        }
+    }
+
+    global_state.set_signals(signals);
+}
+
+/// Whether no car is inside the intersection, on the bike lanes passing it or on one of the
+/// crosswalks
+fn intersection_is_clear(global_state: &GlobalState) -> bool {
+    let dimensions = global_state.get_intersection().get_dimensions();
+    let center = dimensions.get_center();
+    let half_width = dimensions.get_intersection_width() / 2.0
+        + dimensions.get_bike_lane_width()
+        + dimensions.get_crosswalk_width();
+    let area = Rect::new(
+        center.x - half_width,
+        center.y - half_width,
+        2.0 * half_width,
+        2.0 * half_width,
+    );
+
+    global_state
+        .get_cars()
+        .iter()
+        .all(|car| !car.get_footprint().overlaps(&area))
+}
+
+/// Vehicle past its stop line whose movement crosses or merges with the one of `car`. `car`
+/// holds at the stop line until the vehicle has cleared the intersection instead of blocking
+/// the box behind it.
+fn get_conflicting_in_box(car: &Car, global_state: &GlobalState) -> Option<Car> {
+    let dimensions = global_state.get_intersection().get_dimensions();
+
+    if matches!(car.get_vehicle_type(), VehicleType::Emergency | VehicleType::Bicycle)
+        || holds_grant(car, global_state)
+        || car.get_commanded_permission() == Some(true)
+        || !is_approaching_stop_line(car, dimensions)
+    {
+        return None;
+    }
+
+    let center = dimensions.get_center();
+    let half_width = dimensions.get_intersection_width() / 2.0;
+    let area = Rect::new(
+        center.x - half_width,
+        center.y - half_width,
+        2.0 * half_width,
+        2.0 * half_width,
+    );
+
+    car.get_perceived_cars(global_state)
+        .find(|other| {
+            // Vehicles leaving the intersection only get in the way once they're stuck in it
+            let in_box = if other.is_leaving_intersection() {
+                other.get_speed() == 0.0 && other.get_footprint().overlaps(&area)
+            } else {
+                get_distance_to_stop_line(other, dimensions) < 0.0
+            };
+
+            other.get_vehicle_type() != VehicleType::Bicycle
+                && in_box
+                && (conflicts(
+                    (other.get_direction_from(), other.get_lane()),
+                    (car.get_direction_from(), car.get_lane()),
+                ) || swings_into(car, other, global_state)
+                    || swings_into(other, car, global_state))
+        })
+        .copied()
+}
+
+/// Whether `car` swings into the way of `other` when it turns. Long vehicles sweep across the
+/// lanes next to their own as they turn, so they get in the way of movements that cars make
+/// side by side.
+fn swings_into(car: &Car, other: &Car, global_state: &GlobalState) -> bool {
+    let turned = match car.get_turn_point(global_state) {
+        Some(turn_point) if matches!(car.get_lane(), Lane::Left | Lane::Right) => {
+            car.turned_at(turn_point).get_footprint()
+        }
+        _ => return false,
+    };
+
+    other
+        .get_way_through(global_state)
+        .into_iter()
+        .any(|way| overlaps(turned, way))
+}
+
+/// Vehicle crossing the stretch of bike lane cyclist `car` rides along the intersection, or
+/// about to. Cyclists can't wait between the lanes of the crossing road, so they hold at the
+/// stop line until they can ride past all of them.
+fn get_crossing_bike_lane(car: &Car, global_state: &GlobalState) -> Option<Car> {
+    let intersection = global_state.get_intersection();
+    let dimensions = intersection.get_dimensions();
+
+    if car.get_vehicle_type() != VehicleType::Bicycle || !is_approaching_stop_line(car, dimensions)
+    {
+        return None;
+    }
+
+    let center = dimensions.get_center();
+    let half_length = dimensions.get_intersection_width() / 2.0 + dimensions.get_bike_lane_width();
+    let bike_lane = intersection.get_bike_lane(car.get_current_direction());
+    let stretch = match car.get_current_direction() {
+        Direction::North | Direction::South => Rect::new(
+            bike_lane.x,
+            center.y - half_length,
+            bike_lane.w,
+            2.0 * half_length,
+        ),
+        Direction::East | Direction::West => Rect::new(
+            center.x - half_length,
+            bike_lane.y,
+            2.0 * half_length,
+            bike_lane.h,
+        ),
+        _ => unreachable!(),
+    };
+
+    car.get_perceived_cars(global_state)
+        .find(|other| {
+            other.is_crossing(car) && other.get_reach(dimensions).overlaps(&stretch)
+        })
+        .copied()
+}
+
+/// Whether `car` has to stop at the stop line because the intersection manager hasn't granted
+/// it the crossing yet
+pub fn must_wait_for_grant(car: &Car, global_state: &GlobalState) -> bool {
+    if !global_state.get_config().v2i.enabled
+        || !uses_v2i(car)
+        || car.get_permission() == Permission::Granted
+        || car.get_commanded_permission() == Some(true)
+    {
+        return false;
+    }
+
+    is_approaching_stop_line(car, global_state.get_intersection().get_dimensions())
+}
+
+/// Whether the external controller holds `car` at the stop line
+pub fn must_wait_for_controller(car: &Car, global_state: &GlobalState) -> bool {
+    car.get_commanded_permission() == Some(false)
+        && is_approaching_stop_line(car, global_state.get_intersection().get_dimensions())
+}
+
+/// Whether the car is within a lane width and its stopping distance of the stop line in front
+/// of the crosswalk and hasn't passed it yet.
+fn is_approaching_stop_line(car: &Car, dimensions: Dimensions) -> bool {
+    let distance = get_distance_to_stop_line(car, dimensions);
+
+    distance >= 0.0 && distance <= dimensions.get_lane_width() + car.get_stopping_distance()
+}
+
+/// Whether the car can still stop at the stop line, or has braked past it without entering the
+/// intersection. Unlike `is_approaching_stop_line` a car that couldn't stop in time stays held
+/// on the crosswalk instead of being let into the intersection.
+fn is_held_before_intersection(car: &Car, dimensions: Dimensions) -> bool {
+    car.get_distance_to_intersection(dimensions) >= 0.0
+        && get_distance_to_stop_line(car, dimensions)
+            <= dimensions.get_lane_width() + car.get_stopping_distance()
+}
+
+/// Distance from the front of the car to the stop line in front of the crosswalk, negative once
+/// it has passed it
+pub fn get_distance_to_stop_line(car: &Car, dimensions: Dimensions) -> f32 {
+    car.get_distance_to_intersection(dimensions)
+        - dimensions.get_bike_lane_width()
+        - dimensions.get_crosswalk_width()
+}
@@ -1,36 +1,30 @@
+use crate::constants::LANE_WIDTH;
 use crate::intersection::Point;
 
 #[derive(Debug, Default, Clone, PartialEq, Copy)]
 pub struct Dimensions {
     center: Point,
+    /// Unit the areas the drivers check are tuned in. The vehicles themselves have their real
+    /// size, see `VehicleType::get_length`.
     car_width: f32,
     lane_width: f32,
     safety_distance: f32,
     intersection_width: f32,
+    crosswalk_width: f32,
+    bike_lane_width: f32,
     x_max: f32,
     y_max: f32,
-    speed_unit: f32,
 }
 
 impl Dimensions {
-    pub fn new(width: f32, height: f32) -> Self {
-        let x_max: f32;
-        let y_max: f32;
+    /// The world is laid out in metres, independent of the window. It spans 16:9 with its
+    /// origin in the top left corner, the camera fits it into the window.
+    pub fn new() -> Self {
+        let lane_width = LANE_WIDTH;
+        let x_max = lane_width * 15.0;
+        let y_max = x_max / (16.0 / 9.0);
 
-        // aspect ratio >= 16:9
-        if width / height >= (16.0 / 9.0) {
-            y_max = height / 2.0;
-            x_max = y_max * (16.0 / 9.0);
-        } else {
-            x_max = width / 2.0;
-            y_max = x_max / (16.0 / 9.0);
-        };
-
-        let center = Point {
-            x: width / 2.0,
-            y: height / 2.0,
-        };
-        let lane_width = x_max / 15.0;
+        let center = Point { x: x_max, y: y_max };
         let car_width = lane_width / 4.0;
         Dimensions {
             center,
@@ -38,9 +32,10 @@ impl Dimensions {
             lane_width,
             safety_distance: car_width * 3.0,
             intersection_width: lane_width * 6.0,
+            crosswalk_width: lane_width * 0.6,
+            bike_lane_width: lane_width * 0.5,
             x_max,
             y_max,
-            speed_unit: car_width / 10.0,
         }
     }
 
@@ -61,6 +56,14 @@ impl Dimensions {
         self.intersection_width
     }
 
+    pub fn get_crosswalk_width(&self) -> f32 {
+        self.crosswalk_width
+    }
+
+    pub fn get_bike_lane_width(&self) -> f32 {
+        self.bike_lane_width
+    }
+
     pub fn get_x_max(&self) -> f32 {
         self.x_max
     }
@@ -69,11 +72,12 @@ impl Dimensions {
         self.y_max
     }
 
-    pub fn get_speed_unit(&self) -> f32 {
-        self.speed_unit
-    }
-
     pub fn get_safety_distance(&self) -> f32 {
         self.safety_distance
     }
+
+    /// Width of the thin lines drawn on the road, a pixel in a full HD window
+    pub fn get_line_width(&self) -> f32 {
+        self.lane_width / 64.0
+    }
 }
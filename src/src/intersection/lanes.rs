@@ -5,6 +5,7 @@ pub struct Lanes {
     pub left_axis: f32,
     pub middle_axis: f32,
     pub right_axis: f32,
+    pub bike_axis: f32,
 }
 
 
@@ -13,20 +14,26 @@ pub fn calculate_axis(intersection: &mut Intersection) {
 
     let center = intersection.get_dimensions().get_center();
     let lane_width = intersection.get_dimensions().get_lane_width();
+    // Bike lanes run along the right hand side of the road
+    let bike_offset = 3.0 * lane_width + intersection.get_dimensions().get_bike_lane_width() / 2.0;
 
     intersection.south.right_axis = center.x - 2.5 * lane_width;
     intersection.south.middle_axis = center.x - 1.5 * lane_width;
     intersection.south.left_axis = center.x - 0.5 * lane_width;
+    intersection.south.bike_axis = center.x - bike_offset;
 
     intersection.north.left_axis = center.x + 0.5 * lane_width;
     intersection.north.middle_axis = center.x + 1.5 * lane_width;
     intersection.north.right_axis = center.x + 2.5 * lane_width;
+    intersection.north.bike_axis = center.x + bike_offset;
 
     intersection.west.right_axis = center.y - 2.5 * lane_width;
     intersection.west.middle_axis = center.y - 1.5 * lane_width;
     intersection.west.left_axis = center.y - 0.5 * lane_width;
+    intersection.west.bike_axis = center.y - bike_offset;
 
     intersection.east.left_axis = center.y + 0.5 * lane_width;
     intersection.east.middle_axis = center.y + 1.5 * lane_width;
     intersection.east.right_axis = center.y + 2.5 * lane_width;
+    intersection.east.bike_axis = center.y + bike_offset;
 }
@@ -1,10 +1,12 @@
+pub mod control;
 pub mod dimensions;
 pub mod lanes;
+pub mod signals;
 
 use self::dimensions::*;
 use self::lanes::*;
-use crate::car::Direction;
-use macroquad::{prelude::*, window};
+use crate::car::{Direction, Lane};
+use macroquad::prelude::*;
 
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct Point {
@@ -14,8 +16,6 @@ pub struct Point {
 
 #[derive(Debug, Default, Clone, PartialEq, Copy)]
 pub struct Intersection {
-    window_width: f32,
-    window_height: f32,
     dimensions: Dimensions,
     north: Lanes,
     east: Lanes,
@@ -25,12 +25,8 @@ pub struct Intersection {
 
 impl Intersection {
     pub fn new() -> Self {
-        let width = window::screen_width();
-        let height = window::screen_height();
         let mut intersection = Intersection {
-            window_width: width,
-            window_height: height,
-            dimensions: Dimensions::new(width, height),
+            dimensions: Dimensions::new(),
             north: Lanes::default(),
             east: Lanes::default(),
             south: Lanes::default(),
@@ -44,6 +40,23 @@ impl Intersection {
         let mut i: i8 = 0;
         let center = self.dimensions.get_center();
         let lane_width = self.dimensions.get_lane_width();
+        let line_width = self.dimensions.get_line_width();
+
+        for direction in [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ] {
+            let bike_lane = self.get_bike_lane(direction);
+            draw_rectangle(
+                bike_lane.x,
+                bike_lane.y,
+                bike_lane.w,
+                bike_lane.h,
+                Color::new(0.25, 0.45, 0.3, 1.0),
+            );
+        }
 
         while i < 7 {
             // Left to center
@@ -51,35 +64,75 @@ impl Intersection {
             let y1 = center.y - 3.0 * lane_width + (i as f32 * lane_width);
             let x2 = center.x - 3.0 * lane_width;
             let y2 = y1;
-            draw_line(x1, y1, x2, y2, 1.0, WHITE);
+            draw_line(x1, y1, x2, y2, line_width, WHITE);
 
             // center to right
             let x1 = center.x + 3.0 * lane_width;
             let y1 = center.y - 3.0 * lane_width + (i as f32 * lane_width);
             let x2 = center.x + self.dimensions.get_x_max();
             let y2 = y1;
-            draw_line(x1, y1, x2, y2, 1.0, WHITE);
+            draw_line(x1, y1, x2, y2, line_width, WHITE);
 
             // center to top
             let x1 = center.x - 3.0 * lane_width + (i as f32 * lane_width);
             let y1 = center.y - 3.0 * lane_width;
             let x2 = x1;
             let y2 = center.y - self.dimensions.get_y_max();
-            draw_line(x1, y1, x2, y2, 1.0, WHITE);
+            draw_line(x1, y1, x2, y2, line_width, WHITE);
 
             // center to bottom
             let x1 = center.x - 3.0 * lane_width + (i as f32 * lane_width);
             let y1 = center.y + 3.0 * lane_width;
             let x2 = x1;
             let y2 = center.y + self.dimensions.get_y_max();
-            draw_line(x1, y1, x2, y2, 1.0, WHITE);
+            draw_line(x1, y1, x2, y2, line_width, WHITE);
 
             i += 1;
         }
+
+        self.draw_crosswalks();
+    }
+
+    /// Zebra stripes on each side of the intersection
+    fn draw_crosswalks(&self) {
+        let lane_width = self.dimensions.get_lane_width();
+        let stripe = lane_width / 4.0;
+
+        for side in [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ] {
+            let crosswalk = self.get_crosswalk(side);
+            let mut offset = stripe / 2.0;
+
+            while offset < crosswalk.w.max(crosswalk.h) {
+                match side {
+                    Direction::North | Direction::South => draw_rectangle(
+                        crosswalk.x + offset,
+                        crosswalk.y,
+                        stripe / 2.0,
+                        crosswalk.h,
+                        LIGHTGRAY,
+                    ),
+                    Direction::East | Direction::West => draw_rectangle(
+                        crosswalk.x,
+                        crosswalk.y + offset,
+                        crosswalk.w,
+                        stripe / 2.0,
+                        LIGHTGRAY,
+                    ),
+                    _ => unreachable!(),
+                }
+                offset += stripe;
+            }
+        }
     }
 
     pub fn draw_axis(&self) {
         let lane_width = self.dimensions.get_lane_width();
+        let line_width = self.dimensions.get_line_width();
         let center = self.dimensions.get_center();
         let start_x = center.x - 3.0 * lane_width;
         let start_y = center.y - 3.0 * lane_width;
@@ -93,7 +146,7 @@ impl Intersection {
                         0.0,
                         start_x + (0.5 + i) * lane_width,
                         center.y * 2.0,
-                        1.0,
+                        line_width,
                         RED,
                     );
 
@@ -102,7 +155,7 @@ impl Intersection {
                         start_y + (0.5 + i) * lane_width,
                         center.x * 2.0,
                         start_y + (0.5 + i) * lane_width,
-                        1.0,
+                        line_width,
                         RED,
                     );
 
@@ -112,26 +165,125 @@ impl Intersection {
         }
     }
 
-    /// If window is resized, recalculate intersection values
-    pub fn has_changed(&self) -> bool {
-        if self.window_height != window::screen_height()
-            || self.window_width != window::screen_width()
-        {
-            return true;
-        }
-        return false;
+    /// Debug overlay: the lane axes and the box of the intersection
+    pub fn draw_debug(&self) {
+        let center = self.dimensions.get_center();
+        let half_width = self.dimensions.get_intersection_width() / 2.0;
+
+        self.draw_axis();
+        draw_rectangle_lines(
+            center.x - half_width,
+            center.y - half_width,
+            2.0 * half_width,
+            2.0 * half_width,
+            2.0 * self.dimensions.get_line_width(),
+            ORANGE,
+        );
     }
 
-    pub fn get_window_width(&self) -> f32 {
-        self.window_width
+    pub fn get_dimensions(&self) -> Dimensions {
+        self.dimensions
     }
 
-    pub fn get_window_height(&self) -> f32 {
-        self.window_height
+    /// Crosswalk on the given side, just outside the intersection and the bike lanes passing it
+    pub fn get_crosswalk(&self, side: Direction) -> Rect {
+        let center = self.dimensions.get_center();
+        let half_width =
+            self.dimensions.get_intersection_width() / 2.0 + self.dimensions.get_bike_lane_width();
+        let crosswalk_width = self.dimensions.get_crosswalk_width();
+
+        match side {
+            Direction::North => Rect::new(
+                center.x - half_width,
+                center.y - half_width - crosswalk_width,
+                2.0 * half_width,
+                crosswalk_width,
+            ),
+            Direction::South => Rect::new(
+                center.x - half_width,
+                center.y + half_width,
+                2.0 * half_width,
+                crosswalk_width,
+            ),
+            Direction::West => Rect::new(
+                center.x - half_width - crosswalk_width,
+                center.y - half_width,
+                crosswalk_width,
+                2.0 * half_width,
+            ),
+            Direction::East => Rect::new(
+                center.x + half_width,
+                center.y - half_width,
+                crosswalk_width,
+                2.0 * half_width,
+            ),
+            _ => unreachable!(),
+        }
     }
 
-    pub fn get_dimensions(&self) -> Dimensions {
-        self.dimensions
+    /// Bike lane used by the cyclists riding in the given direction, across the whole road
+    pub fn get_bike_lane(&self, direction: Direction) -> Rect {
+        let center = self.dimensions.get_center();
+        let bike_lane_width = self.dimensions.get_bike_lane_width();
+        let axis = self.get_lanes(direction).bike_axis;
+        let x_max = self.dimensions.get_x_max();
+        let y_max = self.dimensions.get_y_max();
+
+        match direction {
+            Direction::North | Direction::South => Rect::new(
+                axis - bike_lane_width / 2.0,
+                center.y - y_max,
+                bike_lane_width,
+                2.0 * y_max,
+            ),
+            Direction::East | Direction::West => Rect::new(
+                center.x - x_max,
+                axis - bike_lane_width / 2.0,
+                2.0 * x_max,
+                bike_lane_width,
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Approach direction and lane at `point`, if it lies on a lane leading into the intersection
+    pub fn get_approach_at(&self, point: Vec2) -> Option<(Direction, Lane)> {
+        let center = self.dimensions.get_center();
+        let half_width = self.dimensions.get_intersection_width() / 2.0;
+        let lane_width = self.dimensions.get_lane_width();
+        let bike_lane_width = self.dimensions.get_bike_lane_width();
+
+        for direction in [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ] {
+            // Distance along the road towards the intersection and position across it
+            let (before, across) = match direction {
+                Direction::North => (point.y - center.y - half_width, point.x),
+                Direction::South => (center.y - half_width - point.y, point.x),
+                Direction::West => (point.x - center.x - half_width, point.y),
+                Direction::East => (center.x - half_width - point.x, point.y),
+                _ => unreachable!(),
+            };
+            if before < 0.0 {
+                continue;
+            }
+
+            let lanes = self.get_lanes(direction);
+            for (axis, lane, width) in [
+                (lanes.left_axis, Lane::Left, lane_width),
+                (lanes.middle_axis, Lane::Middle, lane_width),
+                (lanes.right_axis, Lane::Right, lane_width),
+                (lanes.bike_axis, Lane::Bike, bike_lane_width),
+            ] {
+                if (across - axis).abs() <= width / 2.0 {
+                    return Some((direction, lane));
+                }
+            }
+        }
+        None
     }
 
     pub fn get_lanes(&self, direction: Direction) -> Lanes {
@@ -0,0 +1,91 @@
+use macroquad::prelude::{draw_circle, GREEN, ORANGE, RED};
+use serde::{Deserialize, Serialize};
+
+use crate::car::Direction;
+use crate::intersection::Intersection;
+
+/// Phases of the pedestrian signals, scheduled by `control::schedule_walk_phase`
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+pub enum SignalPhase {
+    /// Cars drive, pedestrians wait
+    Vehicles,
+    /// No new cars enter, waiting for the intersection and crosswalks to clear
+    Clearance,
+    /// Pedestrians may start crossing
+    Walk,
+    /// Pedestrians on the crosswalks finish crossing, no new ones start
+    Flashing,
+}
+
+#[derive(Debug, Clone, PartialEq, Copy)]
+pub struct Signals {
+    phase: SignalPhase,
+    phase_time: f32,
+}
+
+impl Default for Signals {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Signals {
+    pub fn new() -> Self {
+        Signals {
+            phase: SignalPhase::Vehicles,
+            phase_time: 0.0,
+        }
+    }
+
+    /// Pedestrian signal lights at both ends of each crosswalk
+    pub fn draw(&self, intersection: &Intersection) {
+        let dimensions = intersection.get_dimensions();
+        let radius = dimensions.get_car_width() / 3.0;
+        let color = match self.phase {
+            SignalPhase::Walk => GREEN,
+            SignalPhase::Flashing if (self.phase_time * 4.0) as i32 % 2 == 0 => ORANGE,
+            _ => RED,
+        };
+
+        for side in [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ] {
+            let crosswalk = intersection.get_crosswalk(side);
+            let center = crosswalk.center();
+
+            match side {
+                Direction::North | Direction::South => {
+                    draw_circle(crosswalk.left() - radius, center.y, radius, color);
+                    draw_circle(crosswalk.right() + radius, center.y, radius, color);
+                }
+                Direction::East | Direction::West => {
+                    draw_circle(center.x, crosswalk.top() - radius, radius, color);
+                    draw_circle(center.x, crosswalk.bottom() + radius, radius, color);
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    //setters
+    pub fn set_phase(&mut self, new_value: SignalPhase) {
+        self.phase = new_value;
+        self.phase_time = 0.0;
+    }
+
+    pub fn set_phase_time(&mut self, new_value: f32) {
+        self.phase_time = new_value;
+    }
+
+    //getters
+    pub fn get_phase(&self) -> SignalPhase {
+        self.phase
+    }
+
+    pub fn get_phase_time(&self) -> f32 {
+        self.phase_time
+    }
+}
@@ -0,0 +1,290 @@
+use macroquad::{
+    prelude::{Color, RED},
+    shapes::{draw_rectangle, draw_rectangle_lines},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use crate::car::{Car, Direction, Lane};
+use crate::controller::CarState;
+use crate::state::{AnimationState, GlobalState};
+use crate::v2i::manager::conflicts;
+
+/// Metres a vehicle may drift off the axis of its lane, about what it overshoots its turn point
+/// in a frame
+const PATH_TOLERANCE: f32 = 0.3;
+
+/// Invariants checked after every step in strict mode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StrictConfig {
+    /// Starts the simulation in strict mode, it can be toggled while running too
+    pub enabled: bool,
+    /// No two vehicle footprints overlap
+    pub overlap: bool,
+    /// No vehicle is in the intersection box with a vehicle of a conflicting movement
+    pub conflicting_movements: bool,
+    /// No vehicle drives at a negative speed
+    pub negative_speed: bool,
+    /// Every vehicle stays on the axis of its lane, or of the lane it turned into
+    pub off_path: bool,
+    /// Seconds of state written to `output` when an invariant is violated
+    pub history: f32,
+    /// JSON-lines file the violations are appended to with the state before them
+    pub output: String,
+}
+
+impl Default for StrictConfig {
+    fn default() -> Self {
+        StrictConfig {
+            enabled: false,
+            overlap: true,
+            conflicting_movements: true,
+            negative_speed: true,
+            off_path: true,
+            history: 5.0,
+            output: String::from("violations.jsonl"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Copy, Serialize)]
+pub enum Invariant {
+    Overlap,
+    ConflictingMovements,
+    NegativeSpeed,
+    OffPath,
+}
+
+impl Invariant {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Invariant::Overlap => "Overlapping vehicles",
+            Invariant::ConflictingMovements => "Conflicting movements in the box",
+            Invariant::NegativeSpeed => "Negative speed",
+            Invariant::OffPath => "Off its path",
+        }
+    }
+}
+
+/// Vehicles that break an invariant
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Violation {
+    pub invariant: Invariant,
+    pub cars: Vec<u64>,
+}
+
+/// State of strict mode: the recent vehicle states and the violations of the last step
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct InvariantChecker {
+    enabled: bool,
+    /// Animation time and vehicles of every step within `StrictConfig::history`, oldest first
+    history: VecDeque<(f32, Vec<CarState>)>,
+    violations: Vec<Violation>,
+}
+
+impl InvariantChecker {
+    pub fn new(config: &StrictConfig) -> Self {
+        InvariantChecker {
+            enabled: config.enabled,
+            history: VecDeque::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    /// Outline around every vehicle that breaks an invariant
+    pub fn draw_violations(&self, global_state: &GlobalState) {
+        let width = global_state
+            .get_intersection()
+            .get_dimensions()
+            .get_line_width()
+            * 4.0;
+
+        for car in global_state.get_cars().iter() {
+            if !self
+                .violations
+                .iter()
+                .any(|violation| violation.cars.contains(&car.get_id()))
+            {
+                continue;
+            }
+
+            let footprint = car.get_footprint();
+            draw_rectangle(
+                footprint.x,
+                footprint.y,
+                footprint.w,
+                footprint.h,
+                Color::new(1.0, 0.0, 0.0, 0.3),
+            );
+            draw_rectangle_lines(
+                footprint.x - width,
+                footprint.y - width,
+                footprint.w + 2.0 * width,
+                footprint.h + 2.0 * width,
+                width,
+                RED,
+            );
+        }
+    }
+
+    /// Forgets the recent state and the violations
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.violations.clear();
+    }
+
+    //setters
+    pub fn set_enabled(&mut self, new_value: bool) {
+        self.enabled = new_value;
+        if !new_value {
+            self.clear();
+        }
+    }
+
+    //getters
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn get_violations(&self) -> &Vec<Violation> {
+        &self.violations
+    }
+}
+
+/// Checks the invariants after a step in strict mode. Pauses the simulation and dumps the recent
+/// state when one is newly broken, a violation that goes on doesn't pause it again. A simulation
+/// that is already paused, or kept stepping headless after the pause, isn't dumped again.
+pub fn check_invariants(global_state: &mut GlobalState) {
+    if !global_state.get_invariants().is_enabled() {
+        return;
+    }
+
+    let config = global_state.get_config().strict.clone();
+    let time = global_state.get_statistics().get_animation_time();
+    let cars = global_state.get_cars();
+    let violations = find_violations(&cars, global_state, &config);
+    let running = global_state.get_animation_state() == AnimationState::Running;
+
+    let checker = global_state.get_invariants_mut();
+    checker
+        .history
+        .push_back((time, cars.iter().map(CarState::new).collect()));
+    while checker
+        .history
+        .front()
+        .is_some_and(|(oldest, _)| *oldest < time - config.history)
+    {
+        checker.history.pop_front();
+    }
+
+    let new_violations: Vec<&Violation> = violations
+        .iter()
+        .filter(|violation| !checker.violations.contains(violation))
+        .collect();
+
+    if running && !new_violations.is_empty() {
+        let record = json!({
+            "time": time,
+            "violations": new_violations,
+            "history": checker
+                .history
+                .iter()
+                .map(|(time, cars)| json!({ "time": time, "cars": cars }))
+                .collect::<Vec<_>>(),
+        });
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.output)
+            .and_then(|mut file| writeln!(file, "{record}"));
+        if let Err(err) = written {
+            eprintln!("Strict mode can't write {}: {err}", config.output);
+        }
+
+        global_state.set_animation_state(AnimationState::Paused);
+    }
+    global_state.get_invariants_mut().violations = violations;
+}
+
+fn find_violations(
+    cars: &[Car],
+    global_state: &GlobalState,
+    config: &StrictConfig,
+) -> Vec<Violation> {
+    let dimensions = global_state.get_intersection().get_dimensions();
+    let mut violations = Vec::new();
+    let violation = |invariant, cars: &[&Car]| Violation {
+        invariant,
+        cars: cars.iter().map(|car| car.get_id()).collect(),
+    };
+
+    for (i, car) in cars.iter().enumerate() {
+        if config.negative_speed && car.get_speed() < 0.0 {
+            violations.push(violation(Invariant::NegativeSpeed, &[car]));
+        }
+
+        if config.off_path && get_path_offset(car, global_state) > PATH_TOLERANCE {
+            violations.push(violation(Invariant::OffPath, &[car]));
+        }
+
+        for other in cars.iter().skip(i + 1) {
+            if config.overlap && car.get_footprint().overlaps(&other.get_footprint()) {
+                violations.push(violation(Invariant::Overlap, &[car, other]));
+            }
+
+            // A vehicle standing still for the other one keeps out of its path, and vehicles
+            // whose paths cross only conflict while they are about to reach the same space
+            if config.conflicting_movements
+                && !is_yielding(car)
+                && !is_yielding(other)
+                && car.is_at_intersection(global_state)
+                && other.is_at_intersection(global_state)
+                && conflicts(
+                    (car.get_direction_from(), car.get_lane()),
+                    (other.get_direction_from(), other.get_lane()),
+                )
+                && car.get_reach(dimensions).overlaps(&other.get_reach(dimensions))
+            {
+                violations.push(violation(Invariant::ConflictingMovements, &[car, other]));
+            }
+        }
+    }
+    violations
+}
+
+/// Whether the vehicle stands still because it waits for something
+fn is_yielding(car: &Car) -> bool {
+    car.get_speed() == 0.0 && car.get_stop_reason().is_some()
+}
+
+/// Distance of the vehicle from the axis of its lane, or from the line it turned onto
+fn get_path_offset(car: &Car, global_state: &GlobalState) -> f32 {
+    let intersection = global_state.get_intersection();
+    let center = intersection.get_dimensions().get_center();
+
+    let axis = if car.get_current_direction() == car.get_direction_from() {
+        let lanes = intersection.get_lanes(car.get_direction_from());
+        match car.get_lane() {
+            Lane::Left => lanes.left_axis,
+            Lane::Middle => lanes.middle_axis,
+            Lane::Right => lanes.right_axis,
+            Lane::Bike => lanes.bike_axis,
+        }
+    } else {
+        match car.get_direction_from() {
+            Direction::North | Direction::South => center.y + car.get_turn_offset(),
+            Direction::East | Direction::West => center.x + car.get_turn_offset(),
+            _ => unreachable!(),
+        }
+    };
+
+    match car.get_current_direction() {
+        Direction::North | Direction::South => (car.get_x() - axis).abs(),
+        Direction::East | Direction::West => (car.get_y() - axis).abs(),
+        _ => unreachable!(),
+    }
+}
@@ -1,6 +1,14 @@
 pub mod state;
 pub mod car;
+pub mod pedestrian;
 pub mod intersection;
 pub mod statistics;
 pub mod config;
-pub mod constants;
\ No newline at end of file
+pub mod constants;
+pub mod v2i;
+pub mod controller;
+pub mod telemetry;
+pub mod random;
+pub mod gym;
+pub mod camera;
+pub mod invariants;
\ No newline at end of file
@@ -1,14 +1,21 @@
 use macroquad::prelude::*;
 use smart_road::car::cars::CarTraits;
 use smart_road::config::init_config;
+use smart_road::controller::ControllerServer;
+use smart_road::pedestrian::pedestrians::PedestrianTraits;
 use smart_road::state::frames::{draw_menu_frame, draw_paused_frame, draw_running_frame};
 use smart_road::state::{AnimationState, GlobalState};
+use smart_road::statistics::safety::SafetyLog;
+use smart_road::telemetry::Telemetry;
 use std::thread::sleep;
 use std::time::Duration;
 
 #[macroquad::main(init_config)]
 async fn main() {
     let mut global_state = GlobalState::new();
+    let mut controller = ControllerServer::start(&global_state.get_config().controller);
+    let mut telemetry = Telemetry::start(&global_state.get_config().telemetry);
+    let mut safety_log = SafetyLog::start(&global_state.get_config().safety);
 
     loop {
         if global_state.get_animation_state() == AnimationState::Menu {
@@ -18,46 +25,120 @@ async fn main() {
         if global_state.get_animation_state() == AnimationState::Running {
             clear_background(DARKGRAY);
 
-            if global_state.get_intersection().has_changed() {
-                global_state.recalculate_positions();
-            }
+            global_state.update_camera();
+            set_camera(&global_state.get_camera().get_camera_2d());
 
             global_state.get_intersection().draw();
 
-            global_state.get_cars().move_cars(&mut global_state);
+            global_state
+                .get_signals()
+                .draw(&global_state.get_intersection());
 
             global_state
-                .get_cars()
-                .remove_finished_cars(&mut global_state);
+                .get_statistics()
+                .draw_heatmap(global_state.get_heatmap_mode(), &global_state);
+
+            if global_state.get_show_level_of_service() {
+                global_state
+                    .get_statistics()
+                    .draw_level_of_service(&global_state);
+            }
+
+            if let Some(controller) = controller.as_mut() {
+                controller.step(&mut global_state);
+            }
+
+            global_state.simulate_frame();
+
+            if global_state.get_show_trails() {
+                global_state.get_statistics().draw_trails(&global_state);
+            }
 
             global_state.get_cars().draw_cars(&global_state);
 
-            // Time elapsed
-            let mut old_stats = global_state.get_statistics();
+            global_state
+                .get_pedestrians()
+                .draw_pedestrians(&global_state);
 
-            old_stats.set_animation_time(old_stats.get_animation_time() + 1.0 / 60.0);
+            if global_state.get_show_detection_zones() {
+                global_state.get_intersection().draw_debug();
+                global_state.get_cars().draw_detection_zones(&global_state);
+            }
 
-            global_state.set_statistics(old_stats);
+            if global_state.get_show_stop_reasons() {
+                global_state.get_cars().draw_stop_reasons(&global_state);
+            }
+
+            global_state
+                .get_invariants()
+                .draw_violations(&global_state);
 
+            if let Some(telemetry) = telemetry.as_mut() {
+                telemetry.record(&global_state);
+            }
+
+            set_default_camera();
             draw_running_frame(&global_state)
         }
 
         if global_state.get_animation_state() == AnimationState::Paused {
             clear_background(DARKGRAY);
 
-            if global_state.get_intersection().has_changed() {
-                global_state.recalculate_positions();
-            }
+            global_state.update_camera();
+            set_camera(&global_state.get_camera().get_camera_2d());
 
             global_state.get_intersection().draw();
 
+            global_state
+                .get_signals()
+                .draw(&global_state.get_intersection());
+
+            global_state
+                .get_statistics()
+                .draw_heatmap(global_state.get_heatmap_mode(), &global_state);
+
+            if global_state.get_show_level_of_service() {
+                global_state
+                    .get_statistics()
+                    .draw_level_of_service(&global_state);
+            }
+
+            if global_state.get_show_trails() {
+                global_state.get_statistics().draw_trails(&global_state);
+            }
+
             global_state.get_cars().draw_cars(&global_state);
 
+            global_state
+                .get_pedestrians()
+                .draw_pedestrians(&global_state);
+
+            if global_state.get_show_detection_zones() {
+                global_state.get_intersection().draw_debug();
+                global_state.get_cars().draw_detection_zones(&global_state);
+            }
+
+            if global_state.get_show_stop_reasons() {
+                global_state.get_cars().draw_stop_reasons(&global_state);
+            }
+
+            global_state
+                .get_invariants()
+                .draw_violations(&global_state);
+
+            set_default_camera();
             draw_paused_frame(&mut global_state);
         };
 
         global_state.handle_keypress().await;
 
+        // Also right after a reset, which ends the events that were still going on
+        if let Some(safety_log) = safety_log.as_mut() {
+            safety_log.record(&mut global_state);
+        }
+
+        global_state.handle_mouse();
+
         sleep(Duration::new(0, 1_000_000_000u32 / 60));
 
         next_frame().await;
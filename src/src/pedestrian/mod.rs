@@ -0,0 +1,190 @@
+pub mod pedestrians;
+
+use crate::car::Direction;
+use crate::intersection::signals::SignalPhase;
+use crate::random;
+use crate::state::GlobalState;
+use macroquad::prelude::{draw_circle, Color, Vec2, BLACK, YELLOW};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use self::pedestrians::PedestrianTraits;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PedestrianConfig {
+    /// Pedestrians arriving at a random crosswalk per second on average
+    pub rate: f32,
+}
+
+impl Default for PedestrianConfig {
+    fn default() -> Self {
+        PedestrianConfig { rate: 0.05 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Copy)]
+pub struct Pedestrian {
+    x: f32,
+    y: f32,
+    /// Side of the intersection whose crosswalk the pedestrian uses
+    crosswalk: Direction,
+    /// +1.0 when walking towards the bigger coordinate along the crosswalk, -1.0 otherwise
+    heading: f32,
+    speed: f32,
+    state: PedestrianState,
+    waiting_time: f32,
+}
+
+impl Pedestrian {
+    pub fn new(mut crosswalk: Direction, global_state: &GlobalState) -> Self {
+        let mut rng = random::rng();
+        let intersection = global_state.get_intersection();
+        let car_width = intersection.get_dimensions().get_car_width();
+
+        if crosswalk == Direction::Random {
+            crosswalk = match rng.gen_range(0..4) {
+                0 => Direction::North,
+                1 => Direction::South,
+                2 => Direction::East,
+                3 => Direction::West,
+                _ => unreachable!(),
+            };
+        }
+
+        let heading = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+        let area = intersection.get_crosswalk(crosswalk);
+        let center = area.center();
+        // Spread the pedestrians over the width of the crosswalk
+        let spread = rng.gen_range(-0.3..0.3);
+
+        let (x, y) = match crosswalk {
+            Direction::North | Direction::South => (
+                if heading > 0.0 {
+                    area.left() - car_width
+                } else {
+                    area.right() + car_width
+                },
+                center.y + spread * area.h,
+            ),
+            Direction::East | Direction::West => (
+                center.x + spread * area.w,
+                if heading > 0.0 {
+                    area.top() - car_width
+                } else {
+                    area.bottom() + car_width
+                },
+            ),
+            _ => unreachable!(),
+        };
+
+        Pedestrian {
+            x,
+            y,
+            crosswalk,
+            heading,
+            speed: rng.gen_range(1.2..1.8),
+            state: PedestrianState::Waiting,
+            waiting_time: 0.0,
+        }
+    }
+
+    /// Waits at the curb until the walk phase starts and the crosswalk is free of cars, then
+    /// crosses to the other side.
+    pub fn walk(&mut self, global_state: &GlobalState) {
+        let intersection = global_state.get_intersection();
+
+        if self.state == PedestrianState::Waiting {
+            let crosswalk = intersection.get_crosswalk(self.crosswalk);
+            let crosswalk_is_free = global_state
+                .get_cars()
+                .iter()
+                .all(|car| !car.get_footprint().overlaps(&crosswalk));
+
+            if global_state.get_signals().get_phase() == SignalPhase::Walk && crosswalk_is_free {
+                self.state = PedestrianState::Crossing;
+            } else {
+                self.waiting_time += 1.0 / 60.0;
+                return;
+            }
+        }
+
+        let step = self.heading * self.speed / 60.0;
+        match self.crosswalk {
+            Direction::North | Direction::South => self.x += step,
+            Direction::East | Direction::West => self.y += step,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Whether the pedestrian has reached the curb on the other side
+    pub fn has_crossed(&self, global_state: &GlobalState) -> bool {
+        let intersection = global_state.get_intersection();
+        let area = intersection.get_crosswalk(self.crosswalk);
+
+        match self.crosswalk {
+            Direction::North | Direction::South => {
+                (self.heading > 0.0 && self.x > area.right())
+                    || (self.heading < 0.0 && self.x < area.left())
+            }
+            Direction::East | Direction::West => {
+                (self.heading > 0.0 && self.y > area.bottom())
+                    || (self.heading < 0.0 && self.y < area.top())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn draw(&self, global_state: &GlobalState) {
+        let car_width = global_state
+            .get_intersection()
+            .get_dimensions()
+            .get_car_width();
+
+        draw_circle(self.x, self.y, car_width / 3.0, BLACK);
+        draw_circle(
+            self.x,
+            self.y,
+            car_width / 4.0,
+            match self.state {
+                PedestrianState::Waiting => YELLOW,
+                PedestrianState::Crossing => Color::new(1.0, 0.6, 0.2, 1.0),
+            },
+        );
+    }
+
+    //getters
+    pub fn get_position(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    pub fn get_crosswalk(&self) -> Direction {
+        self.crosswalk
+    }
+
+    pub fn get_state(&self) -> PedestrianState {
+        self.state
+    }
+
+    pub fn get_waiting_time(&self) -> f32 {
+        self.waiting_time
+    }
+}
+
+/// Lets a pedestrian arrive at a random crosswalk with `PedestrianConfig::rate`, called every
+/// frame
+pub fn spawn_pedestrians(global_state: &mut GlobalState) {
+    let rate = global_state.get_config().pedestrians.rate;
+
+    if random::rng().gen::<f32>() < rate / 60.0 {
+        let mut pedestrians = global_state.get_pedestrians();
+        pedestrians.add_pedestrian(Pedestrian::new(Direction::Random, global_state));
+        global_state.set_pedestrians(pedestrians);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Copy)]
+pub enum PedestrianState {
+    Waiting,
+    Crossing,
+}
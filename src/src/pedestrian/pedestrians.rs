@@ -0,0 +1,63 @@
+use crate::state::GlobalState;
+
+use super::Pedestrian;
+
+pub type Pedestrians = Vec<Pedestrian>;
+
+pub trait PedestrianTraits {
+    fn new() -> Self;
+    fn add_pedestrian(&mut self, new_value: Pedestrian);
+    fn remove_finished_pedestrians(&mut self, global_state: &mut GlobalState);
+    fn move_pedestrians(&mut self, global_state: &mut GlobalState);
+    fn draw_pedestrians(&self, global_state: &GlobalState);
+}
+
+impl PedestrianTraits for Vec<Pedestrian> {
+    fn new() -> Self {
+        Vec::new()
+    }
+
+    fn add_pedestrian(&mut self, new_value: Pedestrian) {
+        self.push(new_value)
+    }
+
+    fn remove_finished_pedestrians(&mut self, global_state: &mut GlobalState) {
+        let mut new_pedestrians = Pedestrians::new();
+
+        for pedestrian in self.iter() {
+            if !pedestrian.has_crossed(global_state) {
+                new_pedestrians.push(*pedestrian);
+                continue;
+            }
+            add_statistics_pedestrian(pedestrian, global_state)
+        }
+        global_state.set_pedestrians(new_pedestrians);
+    }
+
+    fn move_pedestrians(&mut self, global_state: &mut GlobalState) {
+        for pedestrian in self.iter_mut() {
+            pedestrian.walk(global_state);
+        }
+
+        global_state.set_pedestrians(self.to_owned());
+    }
+
+    fn draw_pedestrians(&self, global_state: &GlobalState) {
+        for pedestrian in self {
+            pedestrian.draw(global_state);
+        }
+    }
+}
+
+fn add_statistics_pedestrian(finished: &Pedestrian, global_state: &mut GlobalState) {
+    let statistics = global_state.get_statistics_mut();
+
+    statistics.set_pedestrians(statistics.get_pedestrians() + 1.0);
+    statistics.set_pedestrian_waiting_time(
+        statistics.get_pedestrian_waiting_time() + finished.get_waiting_time(),
+    );
+
+    if statistics.get_max_pedestrian_waiting_time() <= finished.get_waiting_time() {
+        statistics.set_max_pedestrian_waiting_time(finished.get_waiting_time())
+    }
+}
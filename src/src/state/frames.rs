@@ -1,13 +1,15 @@
 //add drawing -> menu picture with breathing text "Press space to begin the animation"
 
 use macroquad::{
-    prelude::{Color, Vec2, WHITE},
+    prelude::{Color, Vec2, RED, WHITE},
+    shapes::draw_rectangle,
     text::{draw_text_ex, get_text_center, TextParams},
     texture::{draw_texture_ex, DrawTextureParams},
     window,
 };
 
 use super::GlobalState;
+use crate::constants::KMH_PER_MS;
 
 pub fn draw_menu_frame(global_state: &mut GlobalState) {
     draw_background(global_state);
@@ -68,6 +70,10 @@ pub fn draw_menu_frame(global_state: &mut GlobalState) {
 }
 
 pub fn draw_running_frame(global_state: &GlobalState) {
+    if global_state.get_show_time_space() {
+        draw_time_space_view(global_state);
+    }
+
     draw_text_ex(
         "SPACE TO PAUSE",
         10.0,
@@ -89,9 +95,25 @@ pub fn draw_running_frame(global_state: &GlobalState) {
         window::screen_height() / 8.0,
         &global_state,
     );
+
+    if global_state.get_show_charts() {
+        global_state.get_statistics().draw_charts(
+            window::screen_width() * 0.82,
+            window::screen_height() / 8.0,
+            window::screen_width() * 0.16,
+            global_state,
+        );
+    }
+
+    draw_strict_mode(global_state);
+    draw_selected_car(global_state);
 }
 
 pub fn draw_paused_frame(global_state: &mut GlobalState) {
+    if global_state.get_show_time_space() {
+        draw_time_space_view(global_state);
+    }
+
     let text_center = get_text_center(
         "PAUSED",
         Some(global_state.get_text_font()),
@@ -167,6 +189,137 @@ pub fn draw_paused_frame(global_state: &mut GlobalState) {
         window::screen_height() / 8.0,
         &global_state,
     );
+
+    if global_state.get_show_charts() {
+        global_state.get_statistics().draw_charts(
+            window::screen_width() * 0.82,
+            window::screen_height() / 8.0,
+            window::screen_width() * 0.16,
+            global_state,
+        );
+    }
+
+    draw_strict_mode(global_state);
+    draw_selected_car(global_state);
+}
+
+/// Whether strict mode is on and the invariants the last step broke, with the vehicles
+fn draw_strict_mode(global_state: &GlobalState) {
+    let invariants = global_state.get_invariants();
+    if !invariants.is_enabled() {
+        return;
+    }
+
+    let font_size = window::screen_width() / 50.0;
+    let params = |color: Color| TextParams {
+        font: global_state.get_text_font(),
+        font_size: font_size as u16,
+        font_scale: 1.0,
+        font_scale_aspect: 1.0,
+        color,
+        rotation: 0.0,
+    };
+    let bottom = window::screen_height() - 20.0 - 1.5 * font_size;
+    draw_text_ex("STRICT MODE", 10.0, bottom, params(WHITE));
+
+    for (i, violation) in invariants.get_violations().iter().enumerate() {
+        let cars: Vec<String> = violation.cars.iter().map(|id| format!("#{id}")).collect();
+        draw_text_ex(
+            &format!("{}: {}", violation.invariant.get_name(), cars.join(", ")),
+            10.0,
+            bottom - (i as f32 + 1.0) * 1.2 * font_size,
+            params(RED),
+        );
+    }
+}
+
+/// Time-space diagrams covering the intersection, below the statistics and the charts
+fn draw_time_space_view(global_state: &GlobalState) {
+    let (w, h) = (window::screen_width(), window::screen_height());
+    draw_rectangle(0.0, 0.0, w, h, Color::new(0.1, 0.1, 0.1, 0.9));
+
+    // Between the statistics on the left and the charts on the right
+    global_state
+        .get_statistics()
+        .draw_time_space_diagrams(w * 0.15, h * 0.1, w * 0.65, h * 0.8, global_state);
+}
+
+/// Panel with the details of the selected car
+fn draw_selected_car(global_state: &GlobalState) {
+    let car = match global_state.get_selected_car() {
+        Some(car) => car,
+        None => return,
+    };
+
+    let exit = if car.is_leaving_intersection() {
+        car.get_current_direction()
+    } else {
+        car.get_current_direction().turn(car.get_lane())
+    };
+    let stop_reason = match car.get_stop_reason() {
+        Some(reason) => reason.get_description(),
+        None => String::from("-"),
+    };
+
+    let dimensions = global_state.get_intersection().get_dimensions();
+    let distance = car.get_distance_to_intersection(dimensions);
+    let distance = if distance > 0.0 {
+        format!("{distance:.0} m")
+    } else {
+        String::from("-")
+    };
+
+    let details = [
+        format!("Vehicle {}", car.get_id()),
+        format!(
+            "{} | {}",
+            car.get_vehicle_type().get_name(),
+            car.get_driver().get_profile().get_name()
+        ),
+        format!("Speed: {:.0} km/h", car.get_speed() * KMH_PER_MS),
+        format!(
+            "Target Speed: {:.0} km/h",
+            car.get_target_speed() * KMH_PER_MS
+        ),
+        format!(
+            "Route: {:?} {:?} -> {:?}",
+            car.get_direction_from(),
+            car.get_lane(),
+            exit
+        ),
+        format!("To Intersection: {distance}"),
+        format!("Driving Time: {:.1} s", car.get_driving_time()),
+        format!("Stopped For: {stop_reason}"),
+    ];
+
+    let font_size = window::screen_width() / 80.0;
+    let line_height = font_size * 1.3;
+    let x = 10.0;
+    let y = window::screen_height() * 0.55;
+
+    draw_rectangle(
+        x,
+        y,
+        font_size * 22.0,
+        line_height * (details.len() as f32 + 0.5),
+        Color::new(0.0, 0.0, 0.0, 0.5),
+    );
+
+    for (i, line) in details.iter().enumerate() {
+        draw_text_ex(
+            line,
+            x + font_size / 2.0,
+            y + line_height * (i as f32 + 1.0),
+            TextParams {
+                font: global_state.get_text_font(),
+                font_size: font_size as u16,
+                font_scale: 1.0,
+                font_scale_aspect: 1.0,
+                color: WHITE,
+                rotation: 0.0,
+            },
+        );
+    }
 }
 
 fn draw_background(global_state: &GlobalState) {
@@ -1,18 +1,34 @@
 pub mod frames;
 
 use crate::{
+    camera::Camera,
     car::{
         cars::{CarTraits, Cars},
+        deadlocks::{resolve_deadlocks, DeadlockDetector},
+        vehicles::VehicleType,
+        sensors::sense,
         Car, Direction,
     },
-    intersection::Intersection,
-    statistics::Statistics,
+    config::Config,
+    constants::MAX_PERCEPTION_DELAY,
+    intersection::{control::schedule_walk_phase, signals::Signals, Intersection},
+    invariants::{check_invariants, InvariantChecker},
+    pedestrian::{
+        pedestrians::{PedestrianTraits, Pedestrians},
+        spawn_pedestrians, Pedestrian,
+    },
+    statistics::{charts::Sample, heatmap::HeatmapMode, Statistics},
+    v2i::{bus::MessageBus, exchange_messages, manager::IntersectionManager},
 };
 use macroquad::{
-    prelude::{is_key_pressed, ImageFormat, KeyCode, Texture2D},
+    prelude::{
+        is_key_pressed, is_mouse_button_pressed, mouse_position, ImageFormat, KeyCode,
+        MouseButton, Texture2D, Vec2,
+    },
     text::{load_ttf_font_from_bytes, Font},
     window::next_frame,
 };
+use std::collections::{HashMap, VecDeque};
 
 #[derive(Debug, Clone, PartialEq)]
 /// State contains
@@ -22,11 +38,46 @@ pub struct GlobalState {
     menu_background: Texture2D,
     text_font: Font,
 
+    //settings
+    config: Config,
+
     //animation
     intersection: Intersection,
     cars: Cars,
+    /// Cars at the start of each of the last frames, oldest first
+    car_history: VecDeque<Cars>,
+    /// Cars each driver sees through the sensors this frame, by the ID of the driver's car
+    perceptions: HashMap<u64, Cars>,
+    next_car_id: u64,
+    /// Pairs of car IDs that are currently too close to each other
+    close_calls: Vec<(u64, u64)>,
+    deadlock_detector: DeadlockDetector,
+    /// ID of the car whose details are shown
+    selected_car: Option<u64>,
+    /// Debug mode drawing why each stopped car is stopped
+    show_stop_reasons: bool,
+    /// Debug mode drawing the lane axes and the areas the cars check
+    show_detection_zones: bool,
+    /// Statistics panel with the breakdowns by origin, movement and vehicle type
+    statistics_expanded: bool,
+    show_charts: bool,
+    /// Level of service badges next to the approaches
+    show_level_of_service: bool,
+    /// Heatmap drawn over the intersection box
+    heatmap_mode: HeatmapMode,
+    /// Recent path of each vehicle coloured by speed
+    show_trails: bool,
+    /// Time-space diagrams of the approach lanes in place of the intersection
+    show_time_space: bool,
+    /// Strict mode checking the invariants after every step
+    invariants: InvariantChecker,
+    pedestrians: Pedestrians,
+    signals: Signals,
     statistics: Statistics,
+    bus: MessageBus,
+    manager: IntersectionManager,
     animation_state: AnimationState,
+    camera: Camera,
 
     //main menu states
     breathing_opacity: f32,
@@ -35,64 +86,126 @@ pub struct GlobalState {
 
 impl GlobalState {
     pub fn new() -> GlobalState {
+        let mut global_state = Self::headless(Config::load());
+
+        global_state.car_sprite = Texture2D::from_file_with_format(
+            include_bytes!("../../assets/cars.png"),
+            Some(ImageFormat::Png),
+        );
+        global_state.menu_background = Texture2D::from_file_with_format(
+            include_bytes!("../../assets/menu_background.png"),
+            Some(ImageFormat::Png),
+        );
+        global_state.text_font =
+            load_ttf_font_from_bytes(include_bytes!("../../assets/LuckiestGuy-Regular.ttf"))
+                .unwrap();
+        global_state.animation_state = AnimationState::Menu;
+
+        global_state
+    }
+
+    /// State without assets or a window, running from the start. Only `simulate_frame` and the
+    /// other non-drawing methods can be used with it.
+    pub fn headless(config: Config) -> GlobalState {
+        let intersection = Intersection::new();
+        let bus = MessageBus::new(config.v2i.latency, config.v2i.drop_rate);
+        let invariants = InvariantChecker::new(&config.strict);
+
         GlobalState {
-            car_sprite: Texture2D::from_file_with_format(
-                include_bytes!("../../assets/cars.png"),
-                Some(ImageFormat::Png),
-            ),
-            menu_background: Texture2D::from_file_with_format(
-                include_bytes!("../../assets/menu_background.png"),
-                Some(ImageFormat::Png),
-            ),
-
-            text_font: load_ttf_font_from_bytes(include_bytes!(
-                "../../assets/LuckiestGuy-Regular.ttf"
-            ))
-            .unwrap(),
-
-            intersection: Intersection::new(),
+            car_sprite: Texture2D::empty(),
+            menu_background: Texture2D::empty(),
+            text_font: Font::default(),
+
+            config,
+
+            intersection,
             cars: Cars::new(),
+            car_history: VecDeque::new(),
+            perceptions: HashMap::new(),
+            next_car_id: 0,
+            close_calls: Vec::new(),
+            deadlock_detector: DeadlockDetector::new(),
+            selected_car: None,
+            show_stop_reasons: false,
+            show_detection_zones: false,
+            statistics_expanded: false,
+            show_charts: true,
+            show_level_of_service: true,
+            heatmap_mode: HeatmapMode::Off,
+            show_trails: false,
+            show_time_space: false,
+            invariants,
+            pedestrians: Pedestrians::new(),
+            signals: Signals::new(),
             statistics: Statistics::new(),
-            animation_state: AnimationState::Menu,
+            bus,
+            manager: IntersectionManager::new(),
+            animation_state: AnimationState::Running,
+            camera: Camera::new(intersection.get_dimensions()),
             breathing_opacity: 1.0,
             breathing_in: true,
         }
     }
 
-    // FIGURE OUT THE CORRECT CALCULATIONS!
-    pub fn recalculate_positions(&mut self) {
+    /// Advances the simulation by a frame, without drawing anything
+    pub fn simulate_frame(&mut self) {
+        spawn_pedestrians(self);
+
+        schedule_walk_phase(self);
+
+        exchange_messages(self);
+
+        self.get_cars().move_cars(self);
+
+        self.get_cars().remove_finished_cars(self);
+
+        resolve_deadlocks(self);
+
+        self.get_pedestrians().move_pedestrians(self);
+
+        self.get_pedestrians().remove_finished_pedestrians(self);
+
+        let cars = self.get_cars();
         let dimensions = self.get_intersection().get_dimensions();
-        let old_x_max = self.get_intersection().get_dimensions().get_x_max();
-        let old_y_max = self.get_intersection().get_dimensions().get_y_max();
-        let old_center = self.get_intersection().get_dimensions().get_center();
-
-        self.set_intersection(Intersection::new());
-
-        let new_x_max = self.get_intersection().get_dimensions().get_x_max();
-        let new_y_max = dimensions.get_y_max();
-        let x_ratio = old_x_max / new_x_max;
-        let y_ratio = new_y_max / old_y_max;
-
-        let center = self.get_intersection().get_dimensions().get_center();
-
-        let mut cars = self.cars.clone();
-        for car in &mut *cars {
-            match car.get_current_direction() {
-                Direction::North | Direction::South => {
-                    let old_y_from_center = old_center.y - car.get_y();
-                    let new_y = center.y - (old_y_from_center / y_ratio);
-                    car.set_y(new_y);
-                }
-                Direction::East | Direction::West => {
-                    let old_x_from_center = old_center.x - car.get_x();
-                    let new_x = center.x - (old_x_from_center / x_ratio);
-                    car.set_x(new_x);
-                }
-                _ => unreachable!(),
-            }
+        self.get_statistics_mut().update_queues(&cars, dimensions);
+        self.get_statistics_mut()
+            .get_heatmap_mut()
+            .add_frame(&cars, dimensions);
+        self.get_statistics_mut()
+            .update_trajectories(&cars, dimensions);
+        let safety = self.get_config().safety.clone();
+        self.get_statistics_mut()
+            .update_safety(&cars, dimensions, &safety);
+
+        // Time elapsed
+        let time = self.get_statistics().get_animation_time();
+        self.get_statistics_mut().set_animation_time(time + 1.0 / 60.0);
+
+        // Charts are sampled once per simulated second
+        if (time + 1.0 / 60.0).floor() > time.floor() {
+            let sample = Sample::new(self);
+            self.get_statistics_mut().add_sample(sample);
         }
 
-        self.set_cars(cars.to_owned())
+        check_invariants(self);
+    }
+
+    /// Adds the car unless it would spawn on top of another one in its lane
+    pub fn spawn_car(&mut self, mut car: Car) -> bool {
+        let mut cars = self.get_cars();
+
+        // `same_lane_is_clear_of` only sees the cars ahead, not one level with the new car
+        let footprint = car.get_footprint();
+        if cars.iter().any(|other| other.get_footprint().overlaps(&footprint)) {
+            return false;
+        }
+        if car.same_lane_is_clear_of(&cars, self).is_err() {
+            return false;
+        }
+        cars.add_car(car);
+        self.set_cars(cars);
+        self.set_next_car_id(self.get_next_car_id() + 1);
+        true
     }
 
     pub async fn handle_keypress(&mut self) {
@@ -105,37 +218,93 @@ impl GlobalState {
         };
 
         if self.get_animation_state() == AnimationState::Running {
-            let cars = &mut self.get_cars();
             let mut car: Option<Car> = None;
 
             if is_key_pressed(KeyCode::Down) {
-                car = Some(Car::new(Direction::South, &self));
+                car = Some(Car::new(Direction::South, None, &self));
             };
             if is_key_pressed(KeyCode::Up) {
-                car = Some(Car::new(Direction::North, &self));
+                car = Some(Car::new(Direction::North, None, &self));
             };
             if is_key_pressed(KeyCode::Right) {
-                car = Some(Car::new(Direction::East, &self));
+                car = Some(Car::new(Direction::East, None, &self));
             };
             if is_key_pressed(KeyCode::Left) {
-                car = Some(Car::new(Direction::West, &self));
+                car = Some(Car::new(Direction::West, None, &self));
             };
 
             if is_key_pressed(KeyCode::R) {
-                car = Some(Car::new(Direction::Random, &self));
+                car = Some(Car::new(Direction::Random, None, &self));
+            };
+
+            if is_key_pressed(KeyCode::E) {
+                car = Some(Car::new(Direction::Random, Some(VehicleType::Emergency), self));
+            };
+
+            if is_key_pressed(KeyCode::B) {
+                car = Some(Car::new(Direction::Random, Some(VehicleType::Bicycle), self));
+            };
+
+            if is_key_pressed(KeyCode::P) {
+                let pedestrians = &mut self.get_pedestrians();
+                pedestrians.add_pedestrian(Pedestrian::new(Direction::Random, self));
+                self.set_pedestrians(pedestrians.to_owned());
             };
 
-            if let Some(mut new_car) = car {
-                if !new_car.same_lane_is_clear(self) {
-                    return;
-                } else {
-                    cars.add_car(new_car);
-                    self.set_cars(cars.to_owned());
-                    // println!("{:?}", new_car);
-                }
+            if let Some(new_car) = car {
+                self.spawn_car(new_car);
             };
         }
 
+        if self.get_animation_state() != AnimationState::Menu && is_key_pressed(KeyCode::F1) {
+            self.set_show_stop_reasons(!self.get_show_stop_reasons());
+        }
+
+        if self.get_animation_state() != AnimationState::Menu && is_key_pressed(KeyCode::F2) {
+            self.set_show_detection_zones(!self.get_show_detection_zones());
+        }
+
+        if self.get_animation_state() != AnimationState::Menu && is_key_pressed(KeyCode::Tab) {
+            self.set_statistics_expanded(!self.is_statistics_expanded());
+        }
+
+        if self.get_animation_state() != AnimationState::Menu && is_key_pressed(KeyCode::C) {
+            self.set_show_charts(!self.get_show_charts());
+        }
+
+        if self.get_animation_state() != AnimationState::Menu && is_key_pressed(KeyCode::L) {
+            self.set_show_level_of_service(!self.get_show_level_of_service());
+        }
+
+        if self.get_animation_state() != AnimationState::Menu && is_key_pressed(KeyCode::H) {
+            self.set_heatmap_mode(self.get_heatmap_mode().next());
+        }
+
+        if self.get_animation_state() != AnimationState::Menu && is_key_pressed(KeyCode::T) {
+            self.set_show_trails(!self.get_show_trails());
+        }
+
+        if self.get_animation_state() != AnimationState::Menu && is_key_pressed(KeyCode::D) {
+            self.set_show_time_space(!self.get_show_time_space());
+        }
+
+        if self.get_animation_state() != AnimationState::Menu && is_key_pressed(KeyCode::S) {
+            let enabled = self.invariants.is_enabled();
+            self.invariants.set_enabled(!enabled);
+        }
+
+        if self.get_animation_state() != AnimationState::Menu {
+            if is_key_pressed(KeyCode::F) {
+                let following = self.camera.is_following();
+                self.camera
+                    .set_following(!following && self.get_selected_car().is_some());
+            }
+
+            if is_key_pressed(KeyCode::Key0) {
+                self.camera = Camera::new(self.get_intersection().get_dimensions());
+            }
+        }
+
         if self.get_animation_state() == AnimationState::Paused {
             if is_key_pressed(KeyCode::Escape) {
                 self.set_animation_state(AnimationState::Menu);
@@ -146,11 +315,63 @@ impl GlobalState {
         }
     }
 
+    /// Left click selects the car under the cursor, or spawns a car in the clicked approach lane
+    pub fn handle_mouse(&mut self) {
+        if self.get_animation_state() == AnimationState::Menu {
+            return;
+        }
+
+        self.camera.handle_mouse();
+
+        if !is_mouse_button_pressed(MouseButton::Left) {
+            return;
+        }
+
+        let point = self.camera.screen_to_world(Vec2::from(mouse_position()));
+        if let Some(car) = self
+            .get_cars()
+            .iter()
+            .find(|car| car.get_footprint().contains(point))
+        {
+            self.set_selected_car(Some(car.get_id()));
+            return;
+        }
+
+        if self.get_animation_state() == AnimationState::Running {
+            if let Some((direction, lane)) = self.get_intersection().get_approach_at(point) {
+                let car = Car::new_in_lane(direction, None, lane, self);
+                self.spawn_car(car);
+                return;
+            }
+        }
+        self.set_selected_car(None);
+    }
+
+    /// Keeps the followed car in view
+    pub fn update_camera(&mut self) {
+        let target = self
+            .get_selected_car()
+            .map(|car| Vec2::new(car.get_x(), car.get_y()));
+        self.camera.follow(target);
+    }
+
     fn reset(&mut self) {
         self.intersection = Intersection::new();
         self.cars = Cars::new();
-        self.statistics = Statistics::new();
+        self.car_history = VecDeque::new();
+        self.perceptions = HashMap::new();
+        self.next_car_id = 0;
+        self.close_calls = Vec::new();
+        self.deadlock_detector = DeadlockDetector::new();
+        self.invariants.clear();
+        self.selected_car = None;
+        self.pedestrians = Pedestrians::new();
+        self.signals = Signals::new();
+        self.statistics.reset();
+        self.bus = MessageBus::new(self.config.v2i.latency, self.config.v2i.drop_rate);
+        self.manager = IntersectionManager::new();
         self.animation_state = AnimationState::Menu;
+        self.camera = Camera::new(self.get_intersection().get_dimensions());
         self.breathing_opacity = 1.0;
         self.breathing_in = true;
     }
@@ -165,10 +386,111 @@ impl GlobalState {
         self.cars = new_value;
     }
 
+    pub fn set_next_car_id(&mut self, new_value: u64) {
+        self.next_car_id = new_value;
+    }
+
+    /// Remembers the cars at the start of the frame for `get_perceived_cars`, in the buffer of
+    /// the frame that falls out of the history
+    pub fn push_car_history(&mut self) {
+        let max_frames = (MAX_PERCEPTION_DELAY * 60.0) as usize + 1;
+
+        let mut frame = if self.car_history.len() >= max_frames {
+            self.car_history.pop_front().unwrap_or_default()
+        } else {
+            Cars::new()
+        };
+        frame.clone_from(&self.cars);
+        self.car_history.push_back(frame);
+    }
+
+    /// Samples once per frame what every driver sees through the sensors, with its perception
+    /// delay, so the checks of a frame all see the same noisy world
+    pub fn update_perceptions(&mut self) {
+        self.perceptions.clear();
+
+        let sensors = self.config.sensors;
+        if !sensors.enabled {
+            return;
+        }
+
+        let dimensions = self.intersection.get_dimensions();
+        for car in self.cars.iter() {
+            let cars = self
+                .get_perceived_cars(car.get_driver().get_perception_delay())
+                .iter()
+                .filter(|other| other.get_id() != car.get_id())
+                .copied()
+                .collect();
+            let seen = sense(car, cars, &sensors, dimensions);
+            self.perceptions.insert(car.get_id(), seen);
+        }
+    }
+
+    pub fn set_close_calls(&mut self, new_value: Vec<(u64, u64)>) {
+        self.close_calls = new_value;
+    }
+
+    pub fn set_deadlock_detector(&mut self, new_value: DeadlockDetector) {
+        self.deadlock_detector = new_value;
+    }
+
+    pub fn set_selected_car(&mut self, new_value: Option<u64>) {
+        self.selected_car = new_value;
+    }
+
+    pub fn set_show_stop_reasons(&mut self, new_value: bool) {
+        self.show_stop_reasons = new_value;
+    }
+
+    pub fn set_show_detection_zones(&mut self, new_value: bool) {
+        self.show_detection_zones = new_value;
+    }
+
+    pub fn set_statistics_expanded(&mut self, new_value: bool) {
+        self.statistics_expanded = new_value;
+    }
+
+    pub fn set_show_charts(&mut self, new_value: bool) {
+        self.show_charts = new_value;
+    }
+
+    pub fn set_show_level_of_service(&mut self, new_value: bool) {
+        self.show_level_of_service = new_value;
+    }
+
+    pub fn set_heatmap_mode(&mut self, new_value: HeatmapMode) {
+        self.heatmap_mode = new_value;
+    }
+
+    pub fn set_show_trails(&mut self, new_value: bool) {
+        self.show_trails = new_value;
+    }
+
+    pub fn set_show_time_space(&mut self, new_value: bool) {
+        self.show_time_space = new_value;
+    }
+
+    pub fn set_pedestrians(&mut self, new_value: Pedestrians) {
+        self.pedestrians = new_value;
+    }
+
+    pub fn set_signals(&mut self, new_value: Signals) {
+        self.signals = new_value;
+    }
+
     pub fn set_statistics(&mut self, new_value: Statistics) {
         self.statistics = new_value;
     }
 
+    pub fn set_bus(&mut self, new_value: MessageBus) {
+        self.bus = new_value;
+    }
+
+    pub fn set_manager(&mut self, new_value: IntersectionManager) {
+        self.manager = new_value;
+    }
+
     pub fn set_animation_state(&mut self, new_value: AnimationState) {
         self.animation_state = new_value;
     }
@@ -190,6 +512,10 @@ impl GlobalState {
 
     //getters
 
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
     pub fn get_intersection(&self) -> Intersection {
         self.intersection
     }
@@ -198,8 +524,103 @@ impl GlobalState {
         self.cars.clone()
     }
 
-    pub fn get_statistics(&self) -> Statistics {
-        self.statistics
+    pub fn get_next_car_id(&self) -> u64 {
+        self.next_car_id
+    }
+
+    /// The cars as they were `delay` seconds ago, or as far back as the history goes
+    pub fn get_perceived_cars(&self, delay: f32) -> &Cars {
+        let frames = (delay * 60.0).round() as usize;
+
+        if frames == 0 || self.car_history.is_empty() {
+            return &self.cars;
+        }
+
+        let index = self.car_history.len().saturating_sub(frames + 1);
+        &self.car_history[index]
+    }
+
+    /// What the driver of the car with `id` sees through the sensors this frame, `None` when
+    /// the sensors are disabled or the car only spawned this frame
+    pub fn get_perception(&self, id: u64) -> Option<&Cars> {
+        self.perceptions.get(&id)
+    }
+
+    pub fn get_close_calls(&self) -> Vec<(u64, u64)> {
+        self.close_calls.clone()
+    }
+
+    pub fn get_deadlock_detector(&self) -> &DeadlockDetector {
+        &self.deadlock_detector
+    }
+
+    pub fn get_selected_car(&self) -> Option<Car> {
+        let id = self.selected_car?;
+        self.cars.iter().find(|car| car.get_id() == id).copied()
+    }
+
+    pub fn get_show_stop_reasons(&self) -> bool {
+        self.show_stop_reasons
+    }
+
+    pub fn get_show_detection_zones(&self) -> bool {
+        self.show_detection_zones
+    }
+
+    pub fn is_statistics_expanded(&self) -> bool {
+        self.statistics_expanded
+    }
+
+    pub fn get_show_charts(&self) -> bool {
+        self.show_charts
+    }
+
+    pub fn get_show_level_of_service(&self) -> bool {
+        self.show_level_of_service
+    }
+
+    pub fn get_heatmap_mode(&self) -> HeatmapMode {
+        self.heatmap_mode
+    }
+
+    pub fn get_show_trails(&self) -> bool {
+        self.show_trails
+    }
+
+    pub fn get_show_time_space(&self) -> bool {
+        self.show_time_space
+    }
+
+    pub fn get_pedestrians(&self) -> Pedestrians {
+        self.pedestrians.clone()
+    }
+
+    pub fn get_signals(&self) -> Signals {
+        self.signals
+    }
+
+    pub fn get_statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    pub fn get_statistics_mut(&mut self) -> &mut Statistics {
+        &mut self.statistics
+    }
+
+    pub fn get_invariants(&self) -> &InvariantChecker {
+        &self.invariants
+    }
+
+    pub fn get_invariants_mut(&mut self) -> &mut InvariantChecker {
+        &mut self.invariants
+    }
+
+    pub fn get_bus(&self) -> MessageBus {
+        self.bus.clone()
+    }
+
+    pub fn get_manager(&self) -> IntersectionManager {
+        self.manager.clone()
     }
 
     pub fn get_animation_state(&self) -> AnimationState {
@@ -214,6 +635,10 @@ impl GlobalState {
         self.menu_background.clone()
     }
 
+    pub fn get_camera(&self) -> Camera {
+        self.camera
+    }
+
     pub fn get_text_font(&self) -> Font {
         self.text_font
     }
@@ -0,0 +1,181 @@
+use macroquad::{
+    prelude::{Color, BLUE, GREEN, ORANGE, RED, SKYBLUE, WHITE, YELLOW},
+    shapes::{draw_line, draw_rectangle, draw_rectangle_lines},
+    text::{draw_text_ex, TextParams},
+};
+
+use super::Statistics;
+use crate::car::Direction;
+use crate::constants::{KMH_PER_MS, QUEUE_SPEED, TRAVEL_TIME_BIN};
+use crate::state::GlobalState;
+
+/// Values of a line chart and the color they are drawn in
+type Line = (Vec<f32>, Color);
+
+/// State of the simulation taken once per simulated second for the charts
+#[derive(Debug, Default, Clone, PartialEq, Copy)]
+pub struct Sample {
+    pub vehicles: f32,
+    /// Vehicles that have finished their trip so far
+    pub finished: f32,
+    /// Mean speed of the vehicles in km/h
+    pub mean_speed: f32,
+    /// Waiting vehicles per approach, in the order of `Direction::ALL`
+    pub queues: [f32; 4],
+}
+
+impl Sample {
+    pub fn new(global_state: &GlobalState) -> Self {
+        let cars = global_state.get_cars();
+        let mut queues = [0.0; 4];
+
+        for car in cars.iter() {
+            if !car.is_leaving_intersection() && car.get_speed() < QUEUE_SPEED {
+                queues[car.get_direction_from().get_index()] += 1.0;
+            }
+        }
+
+        let mean_speed = if cars.is_empty() {
+            0.0
+        } else {
+            cars.iter().map(|car| car.get_speed()).sum::<f32>() / cars.len() as f32 * KMH_PER_MS
+        };
+
+        Sample {
+            vehicles: cars.len() as f32,
+            finished: global_state.get_statistics().get_finished_trips() as f32,
+            mean_speed,
+            queues,
+        }
+    }
+}
+
+impl Statistics {
+    /// Time series of the last `CHART_SECONDS` and the travel time histogram, stacked in a
+    /// column of width `w` from `x`, `y`
+    pub fn draw_charts(&self, x: f32, y: f32, w: f32, global_state: &GlobalState) {
+        let h = w / 2.5;
+        let gap = h / 4.0;
+        let samples = self.get_samples();
+        let series = |value: fn(&Sample) -> f32| samples.iter().map(value).collect::<Vec<f32>>();
+
+        // Vehicles finished within the last minute, or since the start during the first one
+        let throughput: Vec<f32> = samples
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| sample.finished - samples[i.saturating_sub(60)].finished)
+            .collect();
+
+        let queues: Vec<Line> = Direction::ALL
+            .iter()
+            .zip([RED, GREEN, BLUE, YELLOW])
+            .map(|(direction, color)| {
+                (
+                    samples
+                        .iter()
+                        .map(|sample| sample.queues[direction.get_index()])
+                        .collect(),
+                    color,
+                )
+            })
+            .collect();
+
+        let charts: [(&str, Vec<Line>); 4] = [
+            (
+                "Vehicles",
+                vec![(series(|sample| sample.vehicles), SKYBLUE)],
+            ),
+            ("Throughput / min", vec![(throughput, GREEN)]),
+            (
+                "Mean Speed km/h",
+                vec![(series(|sample| sample.mean_speed), ORANGE)],
+            ),
+            ("Queues N E S W", queues),
+        ];
+
+        let mut top = y;
+        for (title, lines) in charts.iter() {
+            draw_line_chart(title, lines, x, top, w, h, global_state);
+            top += h + gap;
+        }
+
+        let histogram = self.get_trip_totals().get_travel_time_histogram();
+        draw_histogram("Travel Time s", &histogram, x, top, w, h, global_state);
+    }
+}
+
+fn draw_frame(title: &str, max: f32, x: f32, y: f32, w: f32, h: f32, global_state: &GlobalState) {
+    draw_rectangle(x, y, w, h, Color::new(0.0, 0.0, 0.0, 0.5));
+    draw_rectangle_lines(x, y, w, h, 1.0, Color::new(1.0, 1.0, 1.0, 0.5));
+
+    let params = TextParams {
+        font: global_state.get_text_font(),
+        font_size: (w / 14.0) as u16,
+        font_scale: 1.0,
+        font_scale_aspect: 1.0,
+        color: WHITE,
+        rotation: 0.0,
+    };
+    draw_text_ex(title, x + w / 40.0, y + w / 14.0, params);
+    draw_text_ex(&format!("{max:.0}"), x + w * 0.85, y + w / 14.0, params);
+}
+
+/// Lines scaled to the highest value of all of them, the newest sample on the right
+fn draw_line_chart(
+    title: &str,
+    lines: &[Line],
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    global_state: &GlobalState,
+) {
+    let max = lines
+        .iter()
+        .flat_map(|(values, _)| values.iter())
+        .fold(1.0_f32, |max, value| max.max(*value));
+    draw_frame(title, max, x, y, w, h, global_state);
+
+    let top = y + h * 0.25;
+    let height = h * 0.7;
+    for (values, color) in lines {
+        let step = w / values.len().max(2) as f32;
+        let point = |i: usize| (x + step * i as f32, top + height * (1.0 - values[i] / max));
+
+        for i in 1..values.len() {
+            let (x1, y1) = point(i - 1);
+            let (x2, y2) = point(i);
+            draw_line(x1, y1, x2, y2, 2.0, *color);
+        }
+    }
+}
+
+/// Bars of the trips per `TRAVEL_TIME_BIN` seconds, labelled with where the last bar starts
+fn draw_histogram(
+    title: &str,
+    bins: &[u32],
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    global_state: &GlobalState,
+) {
+    let last = TRAVEL_TIME_BIN * (bins.len() - 1) as f32;
+    draw_frame(title, last, x, y, w, h, global_state);
+
+    let max = bins.iter().fold(1, |max, count| max.max(*count)) as f32;
+    let top = y + h * 0.25;
+    let height = h * 0.7;
+    let bar = w / bins.len() as f32;
+
+    for (i, count) in bins.iter().enumerate() {
+        let bar_height = height * *count as f32 / max;
+        draw_rectangle(
+            x + bar * i as f32 + 1.0,
+            top + height - bar_height,
+            bar - 2.0,
+            bar_height,
+            SKYBLUE,
+        );
+    }
+}
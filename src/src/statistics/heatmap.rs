@@ -0,0 +1,223 @@
+use macroquad::{
+    prelude::{Color, Rect, BLUE, RED, WHITE, YELLOW},
+    shapes::draw_rectangle,
+    text::{draw_text_ex, TextParams},
+};
+
+use super::Statistics;
+use crate::car::cars::Cars;
+use crate::constants::HEATMAP_CELL;
+use crate::intersection::{dimensions::Dimensions, Point};
+use crate::state::GlobalState;
+
+/// What the heatmap over the intersection box shows
+#[derive(Debug, Default, Clone, PartialEq, Copy)]
+pub enum HeatmapMode {
+    #[default]
+    Off,
+    /// Share of the time each cell was covered by a vehicle
+    Occupancy,
+    /// Where the close calls happened
+    CloseCalls,
+}
+
+impl HeatmapMode {
+    pub fn next(&self) -> Self {
+        match self {
+            HeatmapMode::Off => HeatmapMode::Occupancy,
+            HeatmapMode::Occupancy => HeatmapMode::CloseCalls,
+            HeatmapMode::CloseCalls => HeatmapMode::Off,
+        }
+    }
+}
+
+/// Square grid of `HEATMAP_CELL` cells over the intersection box, row by row from its top left
+/// corner
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Heatmap {
+    cells: usize,
+    /// Frames each cell was covered by a vehicle
+    occupancy: Vec<f32>,
+    close_calls: Vec<f32>,
+    frames: f32,
+}
+
+impl Heatmap {
+    pub fn new(dimensions: Dimensions) -> Self {
+        let cells = (dimensions.get_intersection_width() / HEATMAP_CELL).ceil() as usize;
+        Heatmap {
+            cells,
+            occupancy: vec![0.0; cells * cells],
+            close_calls: vec![0.0; cells * cells],
+            frames: 0.0,
+        }
+    }
+
+    /// Top left corner of the box
+    fn get_origin(dimensions: Dimensions) -> Point {
+        let center = dimensions.get_center();
+        let half = dimensions.get_intersection_width() / 2.0;
+        Point {
+            x: center.x - half,
+            y: center.y - half,
+        }
+    }
+
+    /// Index of the cell the point lies in, if it is in the box
+    fn get_cell(&self, point: Point, dimensions: Dimensions) -> Option<usize> {
+        let origin = Self::get_origin(dimensions);
+        let column = ((point.x - origin.x) / HEATMAP_CELL).floor();
+        let row = ((point.y - origin.y) / HEATMAP_CELL).floor();
+        let cells = self.cells as f32;
+
+        if column < 0.0 || row < 0.0 || column >= cells || row >= cells {
+            return None;
+        }
+        Some(row as usize * self.cells + column as usize)
+    }
+
+    /// Cells whose center lies in the footprint
+    pub fn get_covered_cells(&self, footprint: Rect, dimensions: Dimensions) -> Vec<usize> {
+        let origin = Self::get_origin(dimensions);
+        // Cells whose center lies between `start` and `end` along one axis
+        let range = |start: f32, end: f32, origin: f32| {
+            let first = ((start - origin) / HEATMAP_CELL - 0.5).ceil().max(0.0);
+            let last = ((end - origin) / HEATMAP_CELL - 0.5)
+                .floor()
+                .min(self.cells as f32 - 1.0);
+            first as i32..=last as i32
+        };
+
+        let mut cells = Vec::new();
+        for row in range(footprint.top(), footprint.bottom(), origin.y) {
+            for column in range(footprint.left(), footprint.right(), origin.x) {
+                cells.push(row as usize * self.cells + column as usize);
+            }
+        }
+        cells
+    }
+
+    pub fn get_cell_center(&self, cell: usize, dimensions: Dimensions) -> Point {
+        let origin = Self::get_origin(dimensions);
+        Point {
+            x: origin.x + ((cell % self.cells) as f32 + 0.5) * HEATMAP_CELL,
+            y: origin.y + ((cell / self.cells) as f32 + 0.5) * HEATMAP_CELL,
+        }
+    }
+
+    /// Number of cells in the grid
+    pub fn get_cell_count(&self) -> usize {
+        self.cells * self.cells
+    }
+
+    /// Counts the cells covered by a vehicle, called every frame
+    pub fn add_frame(&mut self, cars: &Cars, dimensions: Dimensions) {
+        let mut occupied = vec![false; self.get_cell_count()];
+
+        for car in cars.iter() {
+            for cell in self.get_covered_cells(car.get_footprint(), dimensions) {
+                occupied[cell] = true;
+            }
+        }
+
+        for (occupancy, occupied) in self.occupancy.iter_mut().zip(occupied) {
+            if occupied {
+                *occupancy += 1.0;
+            }
+        }
+        self.frames += 1.0;
+    }
+
+    /// Adds a close call between two vehicles at the point halfway between them
+    pub fn add_close_call(&mut self, first: Rect, second: Rect, dimensions: Dimensions) {
+        let (first, second) = (first.center(), second.center());
+        let point = Point {
+            x: (first.x + second.x) / 2.0,
+            y: (first.y + second.y) / 2.0,
+        };
+
+        if let Some(cell) = self.get_cell(point, dimensions) {
+            self.close_calls[cell] += 1.0;
+        }
+    }
+
+    /// Share of the frames the most occupied cell was covered by a vehicle
+    pub fn get_max_occupancy(&self) -> f32 {
+        if self.frames == 0.0 {
+            return 0.0;
+        }
+        self.occupancy
+            .iter()
+            .fold(0.0_f32, |max, frames| max.max(*frames))
+            / self.frames
+    }
+}
+
+impl Statistics {
+    /// Heatmap of the selected mode over the intersection box, scaled to its hottest cell
+    pub fn draw_heatmap(&self, mode: HeatmapMode, global_state: &GlobalState) {
+        let heatmap = self.get_heatmap();
+        let (values, title) = match mode {
+            HeatmapMode::Occupancy => (
+                &heatmap.occupancy,
+                format!("Occupancy, max {:.0}%", heatmap.get_max_occupancy() * 100.0),
+            ),
+            HeatmapMode::CloseCalls => (
+                &heatmap.close_calls,
+                format!("Close Calls: {}", self.get_close_calls()),
+            ),
+            HeatmapMode::Off => return,
+        };
+
+        let dimensions = global_state.get_intersection().get_dimensions();
+        let origin = Heatmap::get_origin(dimensions);
+        let max = values.iter().fold(0.0_f32, |max, value| max.max(*value));
+
+        for (i, value) in values.iter().enumerate() {
+            if *value == 0.0 {
+                continue;
+            }
+            draw_rectangle(
+                origin.x + (i % heatmap.cells) as f32 * HEATMAP_CELL,
+                origin.y + (i / heatmap.cells) as f32 * HEATMAP_CELL,
+                HEATMAP_CELL,
+                HEATMAP_CELL,
+                get_heat_color(value / max),
+            );
+        }
+
+        let font_size = dimensions.get_lane_width() * 0.3;
+        draw_text_ex(
+            &title,
+            origin.x,
+            origin.y - font_size / 2.0,
+            TextParams {
+                font: global_state.get_text_font(),
+                // Rendered at a readable size and scaled down to the size of the world
+                font_size: 32,
+                font_scale: font_size / 32.0,
+                font_scale_aspect: 1.0,
+                color: WHITE,
+                rotation: 0.0,
+            },
+        );
+    }
+}
+
+/// Blue for the coldest values through yellow to red for the hottest, more opaque the hotter
+fn get_heat_color(heat: f32) -> Color {
+    let lerp = |from: Color, to: Color, t: f32| {
+        Color::new(
+            from.r + (to.r - from.r) * t,
+            from.g + (to.g - from.g) * t,
+            from.b + (to.b - from.b) * t,
+            0.2 + 0.5 * heat,
+        )
+    };
+
+    if heat < 0.5 {
+        lerp(BLUE, YELLOW, heat * 2.0)
+    } else {
+        lerp(YELLOW, RED, heat * 2.0 - 1.0)
+    }
+}
@@ -3,17 +3,102 @@ use macroquad::{
     text::{draw_text_ex, TextParams},
 };
 
-use crate::state::GlobalState;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::VecDeque;
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+use crate::{
+    car::{deadlocks::DeadlockStatistics, vehicles::VehicleType, Direction, Lane},
+    constants::{CHART_SECONDS, KMH_PER_MS},
+    intersection::dimensions::Dimensions,
+    state::GlobalState,
+};
+
+pub mod charts;
+pub mod heatmap;
+pub mod queues;
+pub mod safety;
+pub mod trajectories;
+pub mod trips;
+
+use charts::Sample;
+use heatmap::Heatmap;
+use queues::ApproachStatistics;
+use safety::{SafetyEvent, SafetyMonitor, SafetyStatistics};
+use trajectories::Trajectory;
+use trips::{Summary, Trip, TripTotals};
+
+/// Statistics of a single vehicle class, speeds in metres per second and times in seconds.
+/// Speeds are exported in km/h.
+#[derive(Debug, Default, Clone, PartialEq, Copy, Serialize)]
+pub struct ClassStatistics {
+    pub vehicles: f32,
+    #[serde(rename = "max_speed_kmh", serialize_with = "serialize_kmh")]
+    pub max_speed: f32,
+    pub max_time: f32,
+    pub min_time: f32,
+}
+
+/// Statistics of the whole simulation, see `ClassStatistics` for the units
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Statistics {
     animation_time: f32,
     max_vehicles: f32,
+    #[serde(rename = "max_speed_kmh", serialize_with = "serialize_kmh")]
     max_speed: f32,
+    #[serde(rename = "min_speed_kmh", serialize_with = "serialize_kmh")]
     min_speed: f32,
     max_time: f32,
     min_time: f32,
     close_calls: f32,
+    classes: [ClassStatistics; 6],
+    emergency_vehicles: f32,
+    pedestrians: f32,
+    pedestrian_waiting_time: f32,
+    max_pedestrian_waiting_time: f32,
+    emergency_delay: f32,
+    max_emergency_delay: f32,
+    /// Trips finished so far, the cyclists' included
+    #[serde(skip)]
+    finished_trips: u32,
+    /// One sample per simulated second for the charts, oldest first
+    #[serde(skip)]
+    samples: VecDeque<Sample>,
+    /// Running totals the summaries are made from, in the same groups
+    #[serde(skip)]
+    totals: TripTotals,
+    #[serde(skip)]
+    direction_totals: [TripTotals; 4],
+    #[serde(skip)]
+    movement_totals: [TripTotals; 4],
+    #[serde(skip)]
+    vehicle_type_totals: [TripTotals; 6],
+    /// Summary of every trip
+    summary: Summary,
+    /// Summaries by origin, by movement and by vehicle type
+    #[serde(serialize_with = "serialize_directions")]
+    directions: [Summary; 4],
+    #[serde(serialize_with = "serialize_movements")]
+    movements: [Summary; 4],
+    #[serde(serialize_with = "serialize_vehicle_types")]
+    vehicle_types: [Summary; 6],
+    /// Queues, control delay and level of service by origin and lane
+    #[serde(serialize_with = "serialize_approaches")]
+    approaches: [ApproachStatistics; 4],
+    /// Occupancy and close calls of the cells of the intersection box
+    #[serde(skip)]
+    heatmap: Heatmap,
+    /// Paths of the vehicles for the trails and the time-space diagrams
+    #[serde(skip)]
+    trajectories: Vec<Trajectory>,
+    /// Surrogate safety measures of the vehicle pairs
+    safety: SafetyStatistics,
+    /// Events that have ended and that the safety log hasn't taken yet, oldest first
+    #[serde(skip)]
+    safety_events: Vec<SafetyEvent>,
+    #[serde(skip)]
+    safety_monitor: SafetyMonitor,
+    /// Vehicles waiting for each other and how they were freed
+    deadlocks: DeadlockStatistics,
 }
 
 impl Statistics {
@@ -26,25 +111,153 @@ impl Statistics {
             max_time: 0.0,
             min_time: 0.0,
             close_calls: 0.0,
+            classes: [ClassStatistics::default(); 6],
+            emergency_vehicles: 0.0,
+            pedestrians: 0.0,
+            pedestrian_waiting_time: 0.0,
+            max_pedestrian_waiting_time: 0.0,
+            emergency_delay: 0.0,
+            max_emergency_delay: 0.0,
+            finished_trips: 0,
+            samples: VecDeque::new(),
+            totals: TripTotals::default(),
+            direction_totals: Default::default(),
+            movement_totals: Default::default(),
+            vehicle_type_totals: Default::default(),
+            summary: Summary::default(),
+            directions: [Summary::default(); 4],
+            movements: [Summary::default(); 4],
+            vehicle_types: [Summary::default(); 6],
+            approaches: [ApproachStatistics::default(); 4],
+            heatmap: Heatmap::new(Dimensions::new()),
+            trajectories: Vec::new(),
+            safety: SafetyStatistics::default(),
+            safety_events: Vec::new(),
+            safety_monitor: SafetyMonitor::default(),
+            deadlocks: DeadlockStatistics::default(),
         }
     }
 
+    /// Starts over for a new run. The events still going on end with the old run and stay
+    /// around until the safety log has taken them.
+    pub fn reset(&mut self) {
+        self.end_safety_events();
+        *self = Statistics {
+            safety_events: std::mem::take(&mut self.safety_events),
+            ..Statistics::new()
+        };
+    }
+
+    /// Adds a sample for the charts, forgetting the ones older than `CHART_SECONDS`
+    pub fn add_sample(&mut self, sample: Sample) {
+        self.samples.push_back(sample);
+        if self.samples.len() > CHART_SECONDS {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Adds a finished trip and updates the summaries it belongs to
+    pub fn add_trip(&mut self, trip: Trip) {
+        self.finished_trips += 1;
+        self.add_control_delay(trip.direction_from, trip.lane, trip.control_delay);
+
+        let time = self.animation_time;
+        // Cyclists only show up in their own movement and vehicle type
+        if trip.vehicle_type != VehicleType::Bicycle {
+            let direction = trip.direction_from.get_index();
+            self.totals.add(&trip);
+            self.summary = Summary::new(&self.totals, time);
+            self.direction_totals[direction].add(&trip);
+            self.directions[direction] = Summary::new(&self.direction_totals[direction], time);
+        }
+
+        let movement = trip.lane.get_index();
+        self.movement_totals[movement].add(&trip);
+        self.movements[movement] = Summary::new(&self.movement_totals[movement], time);
+
+        let vehicle_type = trip.vehicle_type.get_index();
+        self.vehicle_type_totals[vehicle_type].add(&trip);
+        self.vehicle_types[vehicle_type] =
+            Summary::new(&self.vehicle_type_totals[vehicle_type], time);
+    }
+
     pub fn draw(&self, x: f32, y: f32, w: f32, h: f32, global_state: &GlobalState) {
-        let stats: Vec<(&str, f32)> = vec![
-            ("Animation Time", self.animation_time.floor()),
-            ("Max Vehicles", self.max_vehicles),
-            ("Max Speed", (self.max_speed* 10.0).floor() / 10.0),
-            ("Min Speed", (self.min_speed* 10.0).floor() / 10.0),
-            ("Max Time", (self.max_time * 10.0).floor() / 10.0),
-            ("Min Time", (self.min_time* 10.0).floor() / 10.0),
-            ("Close Calls", self.close_calls),
+        let stats: Vec<(&str, f32, &str)> = vec![
+            ("Animation Time", self.animation_time.floor(), ""),
+            ("Max Vehicles", self.max_vehicles, ""),
+            (
+                "Max Speed",
+                (self.max_speed * KMH_PER_MS * 10.0).floor() / 10.0,
+                " km/h",
+            ),
+            (
+                "Min Speed",
+                (self.min_speed.max(0.0) * KMH_PER_MS * 10.0).floor() / 10.0,
+                " km/h",
+            ),
+            ("Max Time", (self.max_time * 10.0).floor() / 10.0, " s"),
+            ("Min Time", (self.min_time * 10.0).floor() / 10.0, " s"),
+            ("Close Calls", self.close_calls, ""),
+            ("TTC Conflicts", self.safety.ttc_events, ""),
+            ("PET Conflicts", self.safety.pet_events, ""),
+            ("DRAC Conflicts", self.safety.drac_events, ""),
+            ("Deadlocks", self.deadlocks.deadlocks + self.deadlocks.stalls, ""),
+            (
+                "Deadlocked Time",
+                (self.deadlocks.deadlocked_time * 10.0).floor() / 10.0,
+                " s",
+            ),
+            (
+                "Mean Travel Time",
+                (self.summary.travel_time.mean * 10.0).floor() / 10.0,
+                " s",
+            ),
+            (
+                "Mean Delay",
+                (self.summary.delay.mean * 10.0).floor() / 10.0,
+                " s",
+            ),
+            (
+                "Throughput",
+                self.summary.throughput_per_hour.floor(),
+                " veh/h",
+            ),
+            (
+                "Emergency Delay",
+                (self.get_average_emergency_delay() * 10.0).floor() / 10.0,
+                " s",
+            ),
+            (
+                "Max Emergency Delay",
+                (self.max_emergency_delay * 10.0).floor() / 10.0,
+                " s",
+            ),
+            ("Pedestrians", self.pedestrians, ""),
+            (
+                "Pedestrian Wait",
+                (self.get_average_pedestrian_waiting_time() * 10.0).floor() / 10.0,
+                " s",
+            ),
+            (
+                "Max Pedestrian Wait",
+                (self.max_pedestrian_waiting_time * 10.0).floor() / 10.0,
+                " s",
+            ),
         ];
 
-        let top_margin = h / stats.len() as f32 * 1.5;
-        let left_margin = w / stats.len() as f32;
+        let top_margin = h / 8.0;
+        let left_margin = w / 12.0;
+        let params = |font_size: f32| TextParams {
+            font: global_state.get_text_font(),
+            font_size: font_size as u16,
+            font_scale: 1.0,
+            font_scale_aspect: 1.0,
+            color: WHITE,
+            rotation: 0.0,
+        };
 
         let mut i = 0.0;
-        for (key, val) in stats.iter() {
+        for (key, val, unit) in stats.iter() {
             i = i + 1.0;
 
             if *key == "Animation Time" {
@@ -53,31 +266,116 @@ impl Statistics {
                     &format!("{key}: {time}"),
                     x + left_margin,
                     y + top_margin * i,
-                    TextParams {
-                        font: global_state.get_text_font(),
-                        font_size: (w / 10.0) as u16,
-                        font_scale: 1.0,
-                        font_scale_aspect: 1.0,
-                        color: WHITE,
-                        rotation: 0.0,
-                    },
+                    params(w / 10.0),
                 );
                 continue;
             }
 
             draw_text_ex(
-                &format!("{key}: {val}"),
+                &format!("{key}: {val}{unit}"),
+                x + left_margin,
+                y + top_margin * i,
+                params(w / 10.0),
+            );
+        }
+
+        if !global_state.is_statistics_expanded() {
+            for vehicle_type in VehicleType::ALL {
+                i += 1.0;
+
+                let class = self.get_class_statistics(vehicle_type);
+                draw_text_ex(
+                    &format!(
+                        "{}: {} | {} km/h | {}-{} s",
+                        vehicle_type.get_name(),
+                        class.vehicles,
+                        (class.max_speed * KMH_PER_MS * 10.0).floor() / 10.0,
+                        (class.min_time * 10.0).floor() / 10.0,
+                        (class.max_time * 10.0).floor() / 10.0,
+                    ),
+                    x + left_margin,
+                    y + top_margin * i,
+                    params(w / 14.0),
+                );
+            }
+            return;
+        }
+
+        // Expanded view: travel time mean, median, p95 and standard deviation, mean delay, stops
+        // per vehicle and throughput of every group
+        let sections: [(&str, Vec<(&str, Summary)>); 4] = [
+            ("All Vehicles", vec![("All", self.summary)]),
+            (
+                "By Origin",
+                Direction::ALL
+                    .iter()
+                    .map(|direction| {
+                        (
+                            Self::get_direction_name(*direction),
+                            self.get_direction_summary(*direction),
+                        )
+                    })
+                    .collect(),
+            ),
+            (
+                "By Movement",
+                Lane::ALL
+                    .iter()
+                    .map(|lane| (lane.get_movement_name(), self.get_movement_summary(*lane)))
+                    .collect(),
+            ),
+            (
+                "By Vehicle Type",
+                VehicleType::ALL
+                    .iter()
+                    .map(|vehicle_type| {
+                        (
+                            vehicle_type.get_name(),
+                            self.get_vehicle_type_summary(*vehicle_type),
+                        )
+                    })
+                    .collect(),
+            ),
+        ];
+
+        for (title, rows) in sections.iter() {
+            i += 1.0;
+            draw_text_ex(
+                &format!("{title} (travel time mean/median/p95 ±std | delay | stops | per hour)"),
                 x + left_margin,
                 y + top_margin * i,
-                TextParams {
-                    font: global_state.get_text_font(),
-                    font_size: (w / 10.0) as u16,
-                    font_scale: 1.0,
-                    font_scale_aspect: 1.0,
-                    color: WHITE,
-                    rotation: 0.0,
-                },
+                params(w / 14.0),
             );
+
+            for (name, summary) in rows.iter() {
+                i += 0.75;
+                draw_text_ex(
+                    &format!(
+                        "{name}: {} | {:.1}/{:.1}/{:.1} ±{:.1} s | {:.1} s | {:.2} | {:.0}",
+                        summary.vehicles,
+                        summary.travel_time.mean,
+                        summary.travel_time.median,
+                        summary.travel_time.p95,
+                        summary.travel_time.std_dev,
+                        summary.delay.mean,
+                        summary.stops_per_vehicle,
+                        summary.throughput_per_hour,
+                    ),
+                    x + 2.0 * left_margin,
+                    y + top_margin * i,
+                    params(w / 14.0),
+                );
+            }
+        }
+    }
+
+    fn get_direction_name(direction: Direction) -> &'static str {
+        match direction {
+            Direction::North => "Northbound",
+            Direction::East => "Eastbound",
+            Direction::South => "Southbound",
+            Direction::West => "Westbound",
+            _ => unreachable!(),
         }
     }
 
@@ -111,6 +409,14 @@ impl Statistics {
     //setters
     pub fn set_animation_time(&mut self, new_value: f32) {
         self.animation_time = new_value;
+
+        for summary in std::iter::once(&mut self.summary)
+            .chain(self.directions.iter_mut())
+            .chain(self.movements.iter_mut())
+            .chain(self.vehicle_types.iter_mut())
+        {
+            summary.update_throughput(new_value);
+        }
     }
 
     pub fn set_max_vehicles(&mut self, new_value: f32) {
@@ -137,6 +443,38 @@ impl Statistics {
         self.close_calls = new_value
     }
 
+    pub fn set_deadlocks(&mut self, new_value: DeadlockStatistics) {
+        self.deadlocks = new_value
+    }
+
+    pub fn set_emergency_vehicles(&mut self, new_value: f32) {
+        self.emergency_vehicles = new_value
+    }
+
+    pub fn set_emergency_delay(&mut self, new_value: f32) {
+        self.emergency_delay = new_value
+    }
+
+    pub fn set_max_emergency_delay(&mut self, new_value: f32) {
+        self.max_emergency_delay = new_value
+    }
+
+    pub fn set_pedestrians(&mut self, new_value: f32) {
+        self.pedestrians = new_value
+    }
+
+    pub fn set_pedestrian_waiting_time(&mut self, new_value: f32) {
+        self.pedestrian_waiting_time = new_value
+    }
+
+    pub fn set_max_pedestrian_waiting_time(&mut self, new_value: f32) {
+        self.max_pedestrian_waiting_time = new_value
+    }
+
+    pub fn set_class_statistics(&mut self, vehicle_type: VehicleType, new_value: ClassStatistics) {
+        self.classes[vehicle_type.get_index()] = new_value;
+    }
+
     //getters
     pub fn get_animation_time(&self) -> f32 {
         self.animation_time
@@ -165,4 +503,155 @@ impl Statistics {
     pub fn get_close_calls(&self) -> f32 {
         self.close_calls
     }
+
+    /// Emergency vehicles that have left the intersection
+    pub fn get_emergency_vehicles(&self) -> f32 {
+        self.emergency_vehicles
+    }
+
+    /// Total delay of all emergency vehicles that have left the intersection
+    pub fn get_emergency_delay(&self) -> f32 {
+        self.emergency_delay
+    }
+
+    pub fn get_max_emergency_delay(&self) -> f32 {
+        self.max_emergency_delay
+    }
+
+    pub fn get_average_emergency_delay(&self) -> f32 {
+        if self.emergency_vehicles == 0.0 {
+            return 0.0;
+        }
+        self.emergency_delay / self.emergency_vehicles
+    }
+
+    /// Pedestrians that have crossed the road
+    pub fn get_pedestrians(&self) -> f32 {
+        self.pedestrians
+    }
+
+    /// Total time the crossed pedestrians waited at the curb
+    pub fn get_pedestrian_waiting_time(&self) -> f32 {
+        self.pedestrian_waiting_time
+    }
+
+    pub fn get_max_pedestrian_waiting_time(&self) -> f32 {
+        self.max_pedestrian_waiting_time
+    }
+
+    pub fn get_average_pedestrian_waiting_time(&self) -> f32 {
+        if self.pedestrians == 0.0 {
+            return 0.0;
+        }
+        self.pedestrian_waiting_time / self.pedestrians
+    }
+
+    /// Vehicles passed | max speed | min-max time of a vehicle class
+    pub fn get_class_statistics(&self, vehicle_type: VehicleType) -> ClassStatistics {
+        self.classes[vehicle_type.get_index()]
+    }
+
+    pub fn get_finished_trips(&self) -> u32 {
+        self.finished_trips
+    }
+
+    pub fn get_trip_totals(&self) -> &TripTotals {
+        &self.totals
+    }
+
+    pub fn get_samples(&self) -> &VecDeque<Sample> {
+        &self.samples
+    }
+
+    pub fn get_heatmap(&self) -> &Heatmap {
+        &self.heatmap
+    }
+
+    pub fn get_heatmap_mut(&mut self) -> &mut Heatmap {
+        &mut self.heatmap
+    }
+
+    pub fn get_trajectories(&self) -> &Vec<Trajectory> {
+        &self.trajectories
+    }
+
+    pub fn get_safety(&self) -> SafetyStatistics {
+        self.safety
+    }
+
+    pub fn get_deadlocks(&self) -> DeadlockStatistics {
+        self.deadlocks
+    }
+
+    pub fn get_summary(&self) -> Summary {
+        self.summary
+    }
+
+    /// Summary of the vehicles that came from `direction`
+    pub fn get_direction_summary(&self, direction: Direction) -> Summary {
+        self.directions[direction.get_index()]
+    }
+
+    /// Summary of the vehicles that drove through from `lane`
+    pub fn get_movement_summary(&self, lane: Lane) -> Summary {
+        self.movements[lane.get_index()]
+    }
+
+    pub fn get_vehicle_type_summary(&self, vehicle_type: VehicleType) -> Summary {
+        self.vehicle_types[vehicle_type.get_index()]
+    }
+}
+
+/// Writes a speed in metres per second as km/h
+pub fn serialize_kmh<S: Serializer>(speed: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f32(speed * KMH_PER_MS)
+}
+
+/// Reads a speed written by `serialize_kmh` back in metres per second
+pub fn deserialize_kmh<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    Ok(f32::deserialize(deserializer)? / KMH_PER_MS)
+}
+
+fn serialize_directions<S: Serializer>(
+    summaries: &[Summary; 4],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(
+        Direction::ALL
+            .iter()
+            .map(|direction| (format!("{direction:?}"), summaries[direction.get_index()])),
+    )
+}
+
+fn serialize_approaches<S: Serializer>(
+    approaches: &[ApproachStatistics; 4],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(
+        Direction::ALL
+            .iter()
+            .map(|direction| (format!("{direction:?}"), approaches[direction.get_index()])),
+    )
+}
+
+fn serialize_movements<S: Serializer>(
+    summaries: &[Summary; 4],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(
+        Lane::ALL
+            .iter()
+            .map(|lane| (lane.get_movement_name(), summaries[lane.get_index()])),
+    )
+}
+
+fn serialize_vehicle_types<S: Serializer>(
+    summaries: &[Summary; 6],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(
+        VehicleType::ALL
+            .iter()
+            .map(|vehicle_type| (vehicle_type.get_name(), summaries[vehicle_type.get_index()])),
+    )
 }
@@ -0,0 +1,260 @@
+use macroquad::{
+    prelude::{Color, BLACK, GREEN, LIME, MAROON, ORANGE, RED, WHITE, YELLOW},
+    shapes::draw_rectangle,
+    text::{draw_text_ex, TextParams},
+};
+use serde::{Serialize, Serializer};
+
+use super::Statistics;
+use crate::car::{cars::Cars, Car, Direction, Lane, StopReason};
+use crate::constants::QUEUE_SPEED;
+use crate::intersection::dimensions::Dimensions;
+use crate::state::GlobalState;
+
+/// Highway Capacity Manual level of service of an unsignalized intersection, graded by the mean
+/// control delay. The vehicles have no signal of their own and find their gaps like at a two-way
+/// or all-way stop, the walk phase only holds them for the pedestrians, so the unsignalized
+/// thresholds apply rather than the longer signalized ones.
+#[derive(Debug, Default, Clone, PartialEq, Copy, Serialize)]
+pub enum LevelOfService {
+    /// Up to 10 seconds
+    #[default]
+    A,
+    /// Up to 15 seconds
+    B,
+    /// Up to 25 seconds
+    C,
+    /// Up to 35 seconds
+    D,
+    /// Up to 50 seconds
+    E,
+    /// Over 50 seconds
+    F,
+}
+
+impl LevelOfService {
+    pub fn new(control_delay: f32) -> Self {
+        match control_delay {
+            delay if delay <= 10.0 => LevelOfService::A,
+            delay if delay <= 15.0 => LevelOfService::B,
+            delay if delay <= 25.0 => LevelOfService::C,
+            delay if delay <= 35.0 => LevelOfService::D,
+            delay if delay <= 50.0 => LevelOfService::E,
+            _ => LevelOfService::F,
+        }
+    }
+
+    pub fn get_color(&self) -> Color {
+        match self {
+            LevelOfService::A => GREEN,
+            LevelOfService::B => LIME,
+            LevelOfService::C => YELLOW,
+            LevelOfService::D => ORANGE,
+            LevelOfService::E => RED,
+            LevelOfService::F => MAROON,
+        }
+    }
+}
+
+/// Queue and delay of the vehicles coming from one approach lane
+#[derive(Debug, Default, Clone, PartialEq, Copy, Serialize)]
+pub struct LaneStatistics {
+    /// Vehicles currently queued in front of the intersection
+    pub stopped: f32,
+    /// Longest queue so far in vehicles
+    pub max_queue: f32,
+    /// Longest queue so far in metres from the edge of the intersection
+    pub max_queue_length: f32,
+    /// Vehicles that have finished their trip
+    pub vehicles: f32,
+    /// Mean control delay of those vehicles in seconds
+    pub control_delay: f32,
+    pub level_of_service: LevelOfService,
+}
+
+impl LaneStatistics {
+    fn add_delay(&mut self, delay: f32) {
+        self.vehicles += 1.0;
+        self.control_delay += (delay - self.control_delay) / self.vehicles;
+        self.level_of_service = LevelOfService::new(self.control_delay);
+    }
+}
+
+/// Queues and delay of every lane of an approach, see `LaneStatistics`
+#[derive(Debug, Default, Clone, PartialEq, Copy, Serialize)]
+pub struct ApproachStatistics {
+    pub stopped: f32,
+    pub vehicles: f32,
+    pub control_delay: f32,
+    pub level_of_service: LevelOfService,
+    #[serde(serialize_with = "serialize_lanes")]
+    pub lanes: [LaneStatistics; 4],
+}
+
+impl Statistics {
+    /// Counts the queued vehicles of every approach lane, called every frame. A vehicle slower
+    /// than `QUEUE_SPEED` is queued while it is held or stands behind a queued vehicle, not while
+    /// it is only pulling away like a vehicle that has just spawned.
+    pub fn update_queues(&mut self, cars: &Cars, dimensions: Dimensions) {
+        let mut queues = [[(0.0, 0.0); 4]; 4];
+        // Whether the vehicle in front in each lane is queued, going from the front to the back
+        let mut behind_queued = [[false; 4]; 4];
+
+        let mut approaching: Vec<&Car> =
+            cars.iter().filter(|car| !car.is_leaving_intersection()).collect();
+        approaching.sort_by(|a, b| {
+            a.get_distance_to_intersection(dimensions)
+                .total_cmp(&b.get_distance_to_intersection(dimensions))
+        });
+
+        for car in approaching {
+            let direction = car.get_direction_from().get_index();
+            let lane = car.get_lane().get_index();
+            let held = matches!(
+                car.get_stop_reason(),
+                Some(reason) if reason != StopReason::Reacting
+            );
+            let queued =
+                car.get_speed() < QUEUE_SPEED && (held || behind_queued[direction][lane]);
+            behind_queued[direction][lane] = queued;
+
+            if !queued {
+                continue;
+            }
+
+            let back =
+                car.get_distance_to_intersection(dimensions) + car.get_vehicle_type().get_length();
+            let queue = &mut queues[direction][lane];
+            queue.0 += 1.0;
+            queue.1 = back.max(queue.1);
+        }
+
+        for (approach, queues) in self.approaches.iter_mut().zip(queues) {
+            for (lane, (stopped, length)) in approach.lanes.iter_mut().zip(queues) {
+                lane.stopped = stopped;
+                lane.max_queue = lane.max_queue.max(stopped);
+                lane.max_queue_length = lane.max_queue_length.max(length);
+            }
+            approach.stopped = approach.lanes.iter().map(|lane| lane.stopped).sum();
+        }
+    }
+
+    /// Adds the control delay of a finished vehicle to its approach and lane
+    pub fn add_control_delay(&mut self, direction: Direction, lane: Lane, delay: f32) {
+        let approach = &mut self.approaches[direction.get_index()];
+
+        approach.lanes[lane.get_index()].add_delay(delay);
+        approach.vehicles += 1.0;
+        approach.control_delay += (delay - approach.control_delay) / approach.vehicles;
+        approach.level_of_service = LevelOfService::new(approach.control_delay);
+    }
+
+    pub fn get_approach_statistics(&self, direction: Direction) -> ApproachStatistics {
+        self.approaches[direction.get_index()]
+    }
+
+    /// Badge with the level of service of every approach and its lanes, in the corner of the
+    /// world on the curb side of the approach lanes
+    pub fn draw_level_of_service(&self, global_state: &GlobalState) {
+        let intersection = global_state.get_intersection();
+        let dimensions = intersection.get_dimensions();
+        let center = dimensions.get_center();
+        let lane_width = dimensions.get_lane_width();
+        let edge = dimensions.get_intersection_width() / 2.0
+            + dimensions.get_bike_lane_width()
+            + dimensions.get_crosswalk_width()
+            + lane_width / 2.0;
+        let font_size = lane_width * 0.3;
+        let line_height = font_size * 1.4;
+        let width = 20.0 * font_size;
+        let height = 6.0 * line_height;
+
+        for direction in Direction::ALL {
+            let approach = self.get_approach_statistics(direction);
+
+            // Vehicles drive on the right, so the curb is on their right hand side
+            let (x, y) = match direction {
+                Direction::North => (center.x + edge, center.y + edge),
+                Direction::South => (center.x - edge - width, center.y - edge - height),
+                Direction::East => (center.x - edge - width, center.y + edge),
+                Direction::West => (center.x + edge, center.y - edge - height),
+                _ => unreachable!(),
+            };
+            draw_rectangle(x, y, width, height, Color::new(0.0, 0.0, 0.0, 0.6));
+
+            let params = |color: Color| TextParams {
+                font: global_state.get_text_font(),
+                // Rendered at a readable size and scaled down to the size of the world
+                font_size: 32,
+                font_scale: font_size / 32.0,
+                font_scale_aspect: 1.0,
+                color,
+                rotation: 0.0,
+            };
+            let badge = |level: LevelOfService, x: f32, y: f32, size: f32| {
+                draw_rectangle(x, y, size, size, level.get_color());
+                draw_text_ex(
+                    &format!("{level:?}"),
+                    x + size * 0.3,
+                    y + size * 0.8,
+                    TextParams {
+                        font_scale: size * 0.8 / 32.0,
+                        ..params(BLACK)
+                    },
+                );
+            };
+
+            badge(
+                approach.level_of_service,
+                x + font_size / 2.0,
+                y + font_size / 2.0,
+                2.0 * line_height - font_size,
+            );
+            draw_text_ex(
+                &format!(
+                    "{direction:?}bound {:.1} s, {} stopped",
+                    approach.control_delay, approach.stopped
+                ),
+                x + 2.0 * line_height,
+                y + 1.3 * line_height,
+                params(WHITE),
+            );
+
+            for (i, lane) in Lane::ALL.iter().enumerate() {
+                let statistics = approach.lanes[lane.get_index()];
+                let top = y + (i as f32 + 2.0) * line_height;
+
+                badge(
+                    statistics.level_of_service,
+                    x + font_size / 2.0,
+                    top + font_size * 0.2,
+                    font_size,
+                );
+                draw_text_ex(
+                    &format!(
+                        "{}: {} | max {} / {:.0} m | {:.1} s",
+                        lane.get_movement_name(),
+                        statistics.stopped,
+                        statistics.max_queue,
+                        statistics.max_queue_length,
+                        statistics.control_delay
+                    ),
+                    x + 2.0 * font_size,
+                    top + font_size,
+                    params(WHITE),
+                );
+            }
+        }
+    }
+}
+
+fn serialize_lanes<S: Serializer>(
+    lanes: &[LaneStatistics; 4],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(
+        Lane::ALL
+            .iter()
+            .map(|lane| (lane.get_movement_name(), lanes[lane.get_index()])),
+    )
+}
@@ -0,0 +1,317 @@
+use macroquad::prelude::Vec2;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use super::Statistics;
+use crate::car::{cars::Cars, Car, Direction, Lane};
+use crate::intersection::{dimensions::Dimensions, Point};
+use crate::state::GlobalState;
+
+/// Seconds a pair has to stay clear of a threshold before its event ends, so one conflict
+/// isn't split into several events
+const EVENT_GAP: f32 = 1.0;
+
+/// Thresholds of the surrogate safety measures that flag a pair of vehicles
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SafetyConfig {
+    /// Time to collision in seconds below which a pair is flagged
+    pub ttc: f32,
+    /// Post-encroachment time in seconds below which a pair is flagged
+    pub pet: f32,
+    /// Deceleration rate to avoid a crash in metres per second squared above which a pair is
+    /// flagged
+    pub drac: f32,
+    /// JSON-lines file the events are written to, none when empty
+    pub output: String,
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        SafetyConfig {
+            ttc: 1.5,
+            pet: 1.0,
+            drac: 3.35,
+            output: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Copy, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SafetyMeasure {
+    /// Time until the footprints of two vehicles would touch if both kept their velocity
+    Ttc,
+    /// Time between one vehicle leaving a spot of the intersection and another one, coming
+    /// from a different lane, reaching it
+    Pet,
+    /// Deceleration that would just avoid the collision the time to collision predicts
+    Drac,
+}
+
+impl SafetyMeasure {
+    /// Whether `value` is more dangerous than `other`
+    fn is_worse(&self, value: f32, other: f32) -> bool {
+        match self {
+            SafetyMeasure::Ttc | SafetyMeasure::Pet => value < other,
+            SafetyMeasure::Drac => value > other,
+        }
+    }
+}
+
+/// Pair of vehicles past a threshold of `SafetyConfig`, with the worst value of the measure
+/// and where and when it happened
+#[derive(Debug, Clone, PartialEq, Copy, Serialize)]
+pub struct SafetyEvent {
+    pub measure: SafetyMeasure,
+    /// IDs of the vehicles, the lower one first
+    pub cars: (u64, u64),
+    /// Seconds for TTC and PET, metres per second squared for DRAC
+    pub value: f32,
+    pub x: f32,
+    pub y: f32,
+    /// Animation time in seconds
+    pub time: f32,
+}
+
+/// Number of events and the worst value of every measure
+#[derive(Debug, Default, Clone, PartialEq, Copy, Serialize)]
+pub struct SafetyStatistics {
+    pub ttc_events: f32,
+    pub pet_events: f32,
+    pub drac_events: f32,
+    pub min_ttc: Option<f32>,
+    pub min_pet: Option<f32>,
+    pub max_drac: Option<f32>,
+}
+
+impl SafetyStatistics {
+    fn add_event(&mut self, event: &SafetyEvent) {
+        let (events, worst) = match event.measure {
+            SafetyMeasure::Ttc => (&mut self.ttc_events, &mut self.min_ttc),
+            SafetyMeasure::Pet => (&mut self.pet_events, &mut self.min_pet),
+            SafetyMeasure::Drac => (&mut self.drac_events, &mut self.max_drac),
+        };
+
+        *events += 1.0;
+        if worst.is_none_or(|worst| event.measure.is_worse(event.value, worst)) {
+            *worst = Some(event.value);
+        }
+    }
+}
+
+/// Vehicle that last covered a cell of the intersection box
+#[derive(Debug, Clone, PartialEq, Copy)]
+struct Occupant {
+    id: u64,
+    path: (Direction, Lane),
+    time: f32,
+}
+
+/// Events that are still going on and the last vehicle in every cell of the `Heatmap` grid
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SafetyMonitor {
+    /// Events with the last time their pair was past the threshold
+    open: Vec<(SafetyEvent, f32)>,
+    cells: Vec<Option<Occupant>>,
+}
+
+impl Statistics {
+    /// Measures every pair of vehicles and ends the events whose pair has stayed clear of the
+    /// thresholds for `EVENT_GAP`, called every frame
+    pub fn update_safety(&mut self, cars: &Cars, dimensions: Dimensions, config: &SafetyConfig) {
+        let time = self.animation_time;
+        let mut measured = Vec::new();
+        let event = |measure, first: u64, second: u64, value, point: Point| SafetyEvent {
+            measure,
+            cars: (first.min(second), first.max(second)),
+            value,
+            x: point.x,
+            y: point.y,
+            time,
+        };
+
+        for (i, car) in cars.iter().enumerate() {
+            for other in cars.iter().skip(i + 1) {
+                let (ttc, closing_speed) = match get_time_to_collision(car, other) {
+                    Some(collision) => collision,
+                    None => continue,
+                };
+                let point = Point {
+                    x: (car.get_x() + other.get_x()) / 2.0,
+                    y: (car.get_y() + other.get_y()) / 2.0,
+                };
+
+                if ttc < config.ttc {
+                    measured.push(event(
+                        SafetyMeasure::Ttc,
+                        car.get_id(),
+                        other.get_id(),
+                        ttc,
+                        point,
+                    ));
+                }
+                let drac = closing_speed / (2.0 * ttc);
+                if drac > config.drac {
+                    measured.push(event(
+                        SafetyMeasure::Drac,
+                        car.get_id(),
+                        other.get_id(),
+                        drac,
+                        point,
+                    ));
+                }
+            }
+        }
+
+        // Post-encroachment time of every cell a vehicle covers that another vehicle, with a
+        // different path, covered before
+        if self.safety_monitor.cells.is_empty() {
+            self.safety_monitor.cells = vec![None; self.heatmap.get_cell_count()];
+        }
+        for car in cars.iter() {
+            let path = (car.get_direction_from(), car.get_lane());
+
+            for cell in self
+                .heatmap
+                .get_covered_cells(car.get_footprint(), dimensions)
+            {
+                if let Some(previous) = self.safety_monitor.cells[cell] {
+                    let pet = time - previous.time;
+
+                    if previous.id != car.get_id() && previous.path != path && pet < config.pet {
+                        let point = self.heatmap.get_cell_center(cell, dimensions);
+                        measured.push(event(
+                            SafetyMeasure::Pet,
+                            car.get_id(),
+                            previous.id,
+                            pet,
+                            point,
+                        ));
+                    }
+                }
+                self.safety_monitor.cells[cell] = Some(Occupant {
+                    id: car.get_id(),
+                    path,
+                    time,
+                });
+            }
+        }
+
+        let open = &mut self.safety_monitor.open;
+        for event in measured {
+            match open
+                .iter_mut()
+                .find(|(other, _)| other.measure == event.measure && other.cars == event.cars)
+            {
+                Some((other, last_seen)) => {
+                    *last_seen = time;
+                    if event.measure.is_worse(event.value, other.value) {
+                        *other = event;
+                    }
+                }
+                None => open.push((event, time)),
+            }
+        }
+
+        let (ended, open): (Vec<_>, Vec<_>) = open
+            .drain(..)
+            .partition(|(_, last_seen)| *last_seen < time - EVENT_GAP);
+        self.safety_monitor.open = open;
+        for (event, _) in ended {
+            self.add_safety_event(event);
+        }
+    }
+
+    /// Ends the events that are still going on, once the run is over
+    pub fn end_safety_events(&mut self) {
+        for (event, _) in std::mem::take(&mut self.safety_monitor.open) {
+            self.add_safety_event(event);
+        }
+    }
+
+    /// Hands the events that ended since the last call over to the safety log
+    pub fn take_safety_events(&mut self) -> Vec<SafetyEvent> {
+        std::mem::take(&mut self.safety_events)
+    }
+
+    fn add_safety_event(&mut self, event: SafetyEvent) {
+        self.safety.add_event(&event);
+        self.safety_events.push(event);
+    }
+}
+
+/// Seconds until the footprints of the vehicles touch if both keep their velocity, and the
+/// speed they close in at, or none if they never do or already overlap
+fn get_time_to_collision(car: &Car, other: &Car) -> Option<(f32, f32)> {
+    let velocity = |car: &Car| {
+        Vec2::new(car.get_rotation().sin(), -car.get_rotation().cos()) * car.get_speed()
+    };
+    let relative = velocity(car) - velocity(other);
+    let (a, b) = (car.get_footprint(), other.get_footprint());
+
+    // Times the footprints start and stop overlapping along one axis
+    let axis = |speed: f32, a_start: f32, a_end: f32, b_start: f32, b_end: f32| {
+        if speed > 0.0 {
+            Some(((b_start - a_end) / speed, (b_end - a_start) / speed))
+        } else if speed < 0.0 {
+            Some(((b_end - a_start) / speed, (b_start - a_end) / speed))
+        } else if a_end > b_start && b_end > a_start {
+            Some((f32::NEG_INFINITY, f32::INFINITY))
+        } else {
+            None
+        }
+    };
+    let (x_entry, x_exit) = axis(relative.x, a.left(), a.right(), b.left(), b.right())?;
+    let (y_entry, y_exit) = axis(relative.y, a.top(), a.bottom(), b.top(), b.bottom())?;
+
+    let entry = x_entry.max(y_entry);
+    // A negative entry means the footprints already overlap
+    if entry <= 0.0 || entry > x_exit.min(y_exit) {
+        return None;
+    }
+
+    // The axis that overlaps last is the one the vehicles close in along
+    let closing_speed = if x_entry > y_entry {
+        relative.x.abs()
+    } else {
+        relative.y.abs()
+    };
+    Some((entry, closing_speed))
+}
+
+/// Writes the safety events to `SafetyConfig::output` as they end, one JSON object per line
+pub struct SafetyLog {
+    file: BufWriter<File>,
+}
+
+impl SafetyLog {
+    /// Opens the output when one is set in the config
+    pub fn start(config: &SafetyConfig) -> Option<Self> {
+        if config.output.is_empty() {
+            return None;
+        }
+
+        match File::create(&config.output) {
+            Ok(file) => Some(SafetyLog {
+                file: BufWriter::new(file),
+            }),
+            Err(err) => {
+                eprintln!("Safety log can't open {}: {err}", config.output);
+                None
+            }
+        }
+    }
+
+    /// Called every frame, writes the events that ended since the last call
+    pub fn record(&mut self, global_state: &mut GlobalState) {
+        for event in global_state.get_statistics_mut().take_safety_events() {
+            let line = serde_json::to_string(&event).unwrap_or_default();
+            if let Err(err) = writeln!(self.file, "{line}").and_then(|_| self.file.flush()) {
+                eprintln!("Safety log stopped writing: {err}");
+                break;
+            }
+        }
+    }
+}
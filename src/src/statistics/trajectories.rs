@@ -0,0 +1,253 @@
+use macroquad::{
+    prelude::{Color, GREEN, RED, WHITE, YELLOW},
+    shapes::{draw_line, draw_rectangle, draw_rectangle_lines},
+    text::{draw_text_ex, TextParams},
+};
+use std::collections::VecDeque;
+
+use super::Statistics;
+use crate::car::{cars::Cars, Direction, Lane};
+use crate::constants::{TRAIL_SECONDS, TRAJECTORY_SECONDS};
+use crate::intersection::{control::get_distance_to_stop_line, dimensions::Dimensions, Point};
+use crate::state::GlobalState;
+
+/// Frames between two points of a trajectory, 10 points per simulated second
+const TRAJECTORY_FRAMES: u64 = 6;
+
+/// Speed in metres per second drawn in green, about 50 km/h
+const TOP_SPEED: f32 = 14.0;
+
+/// Distances to the stop line shown in the time-space diagrams, in metres
+const DIAGRAM_UPSTREAM: f32 = 40.0;
+const DIAGRAM_DOWNSTREAM: f32 = -20.0;
+
+/// Where a vehicle was at one time
+#[derive(Debug, Default, Clone, PartialEq, Copy)]
+pub struct TrajectoryPoint {
+    pub time: f32,
+    pub position: Point,
+    pub speed: f32,
+    /// Distance from the front of the vehicle to the stop line of its approach, negative once
+    /// it has passed it
+    pub distance: f32,
+}
+
+/// Recent path of a vehicle over the last `TRAJECTORY_SECONDS`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trajectory {
+    pub id: u64,
+    pub direction_from: Direction,
+    pub lane: Lane,
+    /// Oldest first
+    pub points: VecDeque<TrajectoryPoint>,
+}
+
+impl Statistics {
+    /// Adds the current point of every vehicle to its trajectory and forgets the points older
+    /// than `TRAJECTORY_SECONDS`, called every frame
+    pub fn update_trajectories(&mut self, cars: &Cars, dimensions: Dimensions) {
+        let time = self.animation_time;
+        if !((time * 60.0).round() as u64).is_multiple_of(TRAJECTORY_FRAMES) {
+            return;
+        }
+
+        for car in cars.iter() {
+            let index = match self.trajectories.iter().position(|t| t.id == car.get_id()) {
+                Some(index) => index,
+                None => {
+                    self.trajectories.push(Trajectory {
+                        id: car.get_id(),
+                        direction_from: car.get_direction_from(),
+                        lane: car.get_lane(),
+                        points: VecDeque::new(),
+                    });
+                    self.trajectories.len() - 1
+                }
+            };
+            let points = &mut self.trajectories[index].points;
+
+            // The distance to the stop line is unknown once the vehicle leaves the intersection,
+            // from then on it follows from the distance driven
+            let mut distance = get_distance_to_stop_line(car, dimensions);
+            if !distance.is_finite() {
+                distance = match points.back() {
+                    Some(last) => {
+                        last.distance - (last.speed + car.get_speed()) / 2.0 * (time - last.time)
+                    }
+                    None => DIAGRAM_DOWNSTREAM,
+                };
+            }
+
+            points.push_back(TrajectoryPoint {
+                time,
+                position: Point {
+                    x: car.get_x(),
+                    y: car.get_y(),
+                },
+                speed: car.get_speed(),
+                distance,
+            });
+        }
+
+        for trajectory in self.trajectories.iter_mut() {
+            while trajectory
+                .points
+                .front()
+                .is_some_and(|point| point.time < time - TRAJECTORY_SECONDS)
+            {
+                trajectory.points.pop_front();
+            }
+        }
+        self.trajectories
+            .retain(|trajectory| !trajectory.points.is_empty());
+    }
+
+    /// Path of every vehicle on the screen over the last `TRAIL_SECONDS`, coloured by speed and
+    /// fading with age
+    pub fn draw_trails(&self, global_state: &GlobalState) {
+        let time = self.animation_time;
+        let width = global_state
+            .get_intersection()
+            .get_dimensions()
+            .get_lane_width()
+            / 16.0;
+        let cars = global_state.get_cars();
+
+        for trajectory in self.get_trajectories() {
+            if !cars.iter().any(|car| car.get_id() == trajectory.id) {
+                continue;
+            }
+
+            let points = trajectory
+                .points
+                .iter()
+                .skip_while(|point| point.time < time - TRAIL_SECONDS);
+            for (from, to) in points.clone().zip(points.skip(1)) {
+                let mut color = get_speed_color(to.speed);
+                color.a = 1.0 - (time - to.time) / TRAIL_SECONDS;
+                draw_line(
+                    from.position.x,
+                    from.position.y,
+                    to.position.x,
+                    to.position.y,
+                    width,
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Distance to the stop line over time of the vehicles of every approach lane over the last
+    /// `TRAJECTORY_SECONDS`, one diagram per lane with the approaches in rows
+    pub fn draw_time_space_diagrams(
+        &self,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        global_state: &GlobalState,
+    ) {
+        let gap = w / 80.0;
+        let width = (w - 3.0 * gap) / 4.0;
+        let height = (h - 3.0 * gap) / 4.0;
+
+        for direction in Direction::ALL {
+            for lane in Lane::ALL {
+                self.draw_time_space_diagram(
+                    (direction, lane),
+                    x + lane.get_index() as f32 * (width + gap),
+                    y + direction.get_index() as f32 * (height + gap),
+                    width,
+                    height,
+                    global_state,
+                );
+            }
+        }
+    }
+
+    fn draw_time_space_diagram(
+        &self,
+        (direction, lane): (Direction, Lane),
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        global_state: &GlobalState,
+    ) {
+        draw_rectangle(x, y, w, h, Color::new(0.0, 0.0, 0.0, 0.5));
+        draw_rectangle_lines(x, y, w, h, 1.0, Color::new(1.0, 1.0, 1.0, 0.5));
+
+        let params = TextParams {
+            font: global_state.get_text_font(),
+            font_size: (w / 16.0) as u16,
+            font_scale: 1.0,
+            font_scale_aspect: 1.0,
+            color: WHITE,
+            rotation: 0.0,
+        };
+        draw_text_ex(
+            &format!(
+                "{} {}",
+                Self::get_direction_name(direction),
+                lane.get_movement_name()
+            ),
+            x + w / 40.0,
+            y + w / 16.0,
+            params,
+        );
+
+        let time = self.animation_time;
+        let top = y + h * 0.15;
+        let height = h * 0.8;
+        let point = |point: &TrajectoryPoint| {
+            let distance = point.distance.clamp(DIAGRAM_DOWNSTREAM, DIAGRAM_UPSTREAM);
+            (
+                x + w * (1.0 - (time - point.time) / TRAJECTORY_SECONDS),
+                top + height * (DIAGRAM_UPSTREAM - distance)
+                    / (DIAGRAM_UPSTREAM - DIAGRAM_DOWNSTREAM),
+            )
+        };
+
+        // Stop line
+        let (_, stop_line) = point(&TrajectoryPoint::default());
+        draw_line(x, stop_line, x + w, stop_line, 1.0, WHITE);
+        draw_text_ex(
+            &format!("{DIAGRAM_UPSTREAM:.0} m"),
+            x + w * 0.88,
+            top + w / 16.0,
+            params,
+        );
+
+        for trajectory in self
+            .get_trajectories()
+            .iter()
+            .filter(|trajectory| trajectory.direction_from == direction && trajectory.lane == lane)
+        {
+            let points = trajectory.points.iter();
+            for (from, to) in points.clone().zip(points.skip(1)) {
+                let (x1, y1) = point(from);
+                let (x2, y2) = point(to);
+                draw_line(x1, y1, x2, y2, 2.0, get_speed_color(to.speed));
+            }
+        }
+    }
+}
+
+/// Red for standing vehicles through yellow to green at `TOP_SPEED`
+fn get_speed_color(speed: f32) -> Color {
+    let share = (speed / TOP_SPEED).clamp(0.0, 1.0);
+    let lerp = |from: Color, to: Color, t: f32| {
+        Color::new(
+            from.r + (to.r - from.r) * t,
+            from.g + (to.g - from.g) * t,
+            from.b + (to.b - from.b) * t,
+            1.0,
+        )
+    };
+
+    if share < 0.5 {
+        lerp(RED, YELLOW, share * 2.0)
+    } else {
+        lerp(YELLOW, GREEN, share * 2.0 - 1.0)
+    }
+}
@@ -0,0 +1,155 @@
+use serde::Serialize;
+
+use crate::car::{vehicles::VehicleType, Car, Direction, Lane};
+use crate::constants::{TRAVEL_TIME_BIN, TRAVEL_TIME_BINS};
+
+/// A vehicle that has driven through the intersection and left the screen
+#[derive(Debug, Clone, PartialEq, Copy, Serialize)]
+pub struct Trip {
+    pub direction_from: Direction,
+    pub lane: Lane,
+    pub vehicle_type: VehicleType,
+    /// Seconds from spawning to leaving the screen
+    pub travel_time: f32,
+    /// Seconds lost compared to driving through at free flow speed
+    pub delay: f32,
+    /// Part of the delay lost around the stop line and in the intersection
+    pub control_delay: f32,
+    pub stops: u32,
+}
+
+impl Trip {
+    pub fn new(car: &Car) -> Self {
+        Trip {
+            direction_from: car.get_direction_from(),
+            lane: car.get_lane(),
+            vehicle_type: car.get_vehicle_type(),
+            travel_time: car.get_driving_time(),
+            delay: car.get_delay(),
+            control_delay: car.get_control_delay(),
+            stops: car.get_stops(),
+        }
+    }
+}
+
+/// Mean, median, 95th percentile and standard deviation of a set of values
+#[derive(Debug, Default, Clone, PartialEq, Copy, Serialize)]
+pub struct Distribution {
+    pub mean: f32,
+    pub median: f32,
+    pub p95: f32,
+    pub std_dev: f32,
+}
+
+impl Distribution {
+    /// Nearest rank percentile of sorted values
+    fn percentile(sorted: &[f32], share: f32) -> f32 {
+        let rank = (share * sorted.len() as f32).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+}
+
+/// Values kept in order for the percentiles, with their sum and sum of squares for the mean and
+/// the standard deviation
+#[derive(Debug, Default, Clone, PartialEq)]
+struct SortedValues {
+    values: Vec<f32>,
+    sum: f64,
+    sum_of_squares: f64,
+}
+
+impl SortedValues {
+    fn add(&mut self, value: f32) {
+        let index = self.values.partition_point(|other| *other <= value);
+        self.values.insert(index, value);
+        self.sum += value as f64;
+        self.sum_of_squares += (value as f64).powi(2);
+    }
+
+    fn get_distribution(&self) -> Distribution {
+        if self.values.is_empty() {
+            return Distribution::default();
+        }
+
+        let count = self.values.len() as f64;
+        let mean = self.sum / count;
+        let variance = (self.sum_of_squares / count - mean.powi(2)).max(0.0);
+
+        Distribution {
+            mean: mean as f32,
+            median: Distribution::percentile(&self.values, 0.5),
+            p95: Distribution::percentile(&self.values, 0.95),
+            std_dev: variance.sqrt() as f32,
+        }
+    }
+}
+
+/// Running totals of the trips of a group of vehicles, updated trip by trip so the summary
+/// doesn't have to go over all the trips again
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TripTotals {
+    stops: u32,
+    travel_times: SortedValues,
+    delays: SortedValues,
+    /// Trips per `TRAVEL_TIME_BIN` seconds of travel time
+    travel_time_histogram: [u32; TRAVEL_TIME_BINS],
+}
+
+impl TripTotals {
+    pub fn add(&mut self, trip: &Trip) {
+        self.stops += trip.stops;
+        self.travel_times.add(trip.travel_time);
+        self.delays.add(trip.delay);
+
+        let bin = (trip.travel_time / TRAVEL_TIME_BIN) as usize;
+        self.travel_time_histogram[bin.min(TRAVEL_TIME_BINS - 1)] += 1;
+    }
+
+    //getters
+    pub fn get_vehicles(&self) -> usize {
+        self.travel_times.values.len()
+    }
+
+    pub fn get_travel_time_histogram(&self) -> [u32; TRAVEL_TIME_BINS] {
+        self.travel_time_histogram
+    }
+}
+
+/// Statistics of the trips of a group of vehicles, times in seconds
+#[derive(Debug, Default, Clone, PartialEq, Copy, Serialize)]
+pub struct Summary {
+    pub vehicles: f32,
+    pub travel_time: Distribution,
+    pub delay: Distribution,
+    pub stops_per_vehicle: f32,
+    /// Vehicles per hour of simulated time
+    pub throughput_per_hour: f32,
+}
+
+impl Summary {
+    pub fn new(totals: &TripTotals, animation_time: f32) -> Self {
+        let vehicles = totals.get_vehicles() as f32;
+        if vehicles == 0.0 {
+            return Summary::default();
+        }
+
+        let mut summary = Summary {
+            vehicles,
+            travel_time: totals.travel_times.get_distribution(),
+            delay: totals.delays.get_distribution(),
+            stops_per_vehicle: totals.stops as f32 / vehicles,
+            throughput_per_hour: 0.0,
+        };
+        summary.update_throughput(animation_time);
+        summary
+    }
+
+    /// Throughput changes with the time even when no vehicle finishes
+    pub fn update_throughput(&mut self, animation_time: f32) {
+        self.throughput_per_hour = if animation_time > 0.0 {
+            self.vehicles / animation_time * 3600.0
+        } else {
+            0.0
+        };
+    }
+}
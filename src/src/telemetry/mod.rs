@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::constants::CONFIG_FILE;
+use crate::controller::CarState;
+use crate::state::GlobalState;
+
+/// Sections of a telemetry record, in the order they are written
+pub const FIELDS: [&str; 5] = ["time", "signal_phase", "pedestrians", "statistics", "cars"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    /// Path of a JSON-lines file, or `tcp://address` to stream to every client that connects
+    pub output: String,
+    /// Records per second of simulated time
+    pub rate: f32,
+    /// Sections of `FIELDS` to include, all of them when empty
+    pub fields: Vec<String>,
+    /// Fields of `CarState` to include for every car, all of them when empty
+    pub car_fields: Vec<String>,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        TelemetryConfig {
+            enabled: false,
+            output: String::from("telemetry.jsonl"),
+            rate: 10.0,
+            fields: Vec::new(),
+            car_fields: Vec::new(),
+        }
+    }
+}
+
+impl TelemetryConfig {
+    /// Falls back to the default rate when the configured one can't be used
+    pub fn validate(&mut self) {
+        if self.rate <= 0.0 || self.rate.is_nan() {
+            eprintln!(
+                "Ignoring telemetry.rate in {CONFIG_FILE}: {} isn't above 0",
+                self.rate
+            );
+            self.rate = TelemetryConfig::default().rate;
+        }
+    }
+}
+
+enum Sink {
+    File(BufWriter<File>),
+    Socket {
+        listener: TcpListener,
+        clients: Vec<Client>,
+    },
+}
+
+/// Dashboard connected to the telemetry socket
+struct Client {
+    stream: TcpStream,
+    /// Record the client hasn't taken from the socket yet
+    outgoing: Vec<u8>,
+}
+
+impl Client {
+    /// Queues the record unless the client is still behind on the last one, then writes as much
+    /// as the socket takes without blocking. False once the client is gone.
+    fn send(&mut self, line: &str) -> bool {
+        if self.outgoing.is_empty() {
+            self.outgoing.extend_from_slice(line.as_bytes());
+        }
+
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return false,
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return true,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(_) => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Writes a JSON record of the simulation every `1 / rate` seconds
+pub struct Telemetry {
+    sink: Sink,
+    config: TelemetryConfig,
+    /// Simulated seconds since the last record
+    elapsed: f32,
+}
+
+impl Telemetry {
+    /// Opens the output when telemetry is enabled in the config
+    pub fn start(config: &TelemetryConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+
+        let sink = match config.output.strip_prefix("tcp://") {
+            Some(address) => TcpListener::bind(address)
+                .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+                .map(|listener| Sink::Socket {
+                    listener,
+                    clients: Vec::new(),
+                }),
+            None => File::create(&config.output).map(|file| Sink::File(BufWriter::new(file))),
+        };
+
+        match sink {
+            Ok(sink) => Some(Telemetry {
+                sink,
+                config: config.clone(),
+                elapsed: f32::INFINITY,
+            }),
+            Err(err) => {
+                eprintln!("Telemetry can't open {}: {err}", config.output);
+                None
+            }
+        }
+    }
+
+    /// Called every frame, writes a record when one is due
+    pub fn record(&mut self, global_state: &GlobalState) {
+        if !self.config.enabled {
+            return;
+        }
+
+        self.elapsed += 1.0 / 60.0;
+        if self.elapsed < 1.0 / self.config.rate {
+            return;
+        }
+        self.elapsed = 0.0;
+
+        let mut line = self.build_record(global_state).to_string();
+        line.push('\n');
+
+        match &mut self.sink {
+            Sink::File(file) => {
+                if let Err(err) = file.write_all(line.as_bytes()).and_then(|_| file.flush()) {
+                    eprintln!("Telemetry stopped writing: {err}");
+                    self.config.enabled = false;
+                }
+            }
+            Sink::Socket { listener, clients } => {
+                // A dashboard that reads slower than the records come skips the ones it can't
+                // keep up with, instead of holding up the simulation
+                while let Ok((stream, _)) = listener.accept() {
+                    if stream.set_nonblocking(true).is_ok() {
+                        clients.push(Client {
+                            stream,
+                            outgoing: Vec::new(),
+                        });
+                    }
+                }
+                clients.retain_mut(|client| client.send(&line));
+            }
+        }
+    }
+
+    fn build_record(&self, global_state: &GlobalState) -> Value {
+        let cars: Vec<Value> = global_state
+            .get_cars()
+            .iter()
+            .map(|car| select(json!(CarState::new(car)), &self.config.car_fields))
+            .collect();
+
+        let record = json!({
+            "time": global_state.get_statistics().get_animation_time(),
+            "signal_phase": global_state.get_signals().get_phase(),
+            "pedestrians": global_state.get_pedestrians().len(),
+            "statistics": global_state.get_statistics(),
+            "cars": cars,
+        });
+
+        select(record, &self.config.fields)
+    }
+}
+
+/// Keeps only the `fields` of a JSON object, everything when `fields` is empty
+fn select(value: Value, fields: &[String]) -> Value {
+    match value {
+        Value::Object(object) if !fields.is_empty() => Value::Object(
+            object
+                .into_iter()
+                .filter(|(key, _)| fields.contains(key))
+                .collect::<Map<String, Value>>(),
+        ),
+        value => value,
+    }
+}
@@ -0,0 +1,71 @@
+use rand::Rng;
+
+use super::Message;
+use crate::random;
+
+/// In-process radio channel. Messages are encoded to their wire format on `send` and only come
+/// out of `tick` once the latency has passed, unless they were dropped on the way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageBus {
+    /// Encoded messages with the time they arrive
+    in_flight: Vec<(f32, Vec<u8>)>,
+    time: f32,
+    latency: f32,
+    drop_rate: f32,
+    sent: u32,
+    dropped: u32,
+}
+
+impl MessageBus {
+    pub fn new(latency: f32, drop_rate: f32) -> Self {
+        MessageBus {
+            in_flight: Vec::new(),
+            time: 0.0,
+            latency,
+            drop_rate,
+            sent: 0,
+            dropped: 0,
+        }
+    }
+
+    pub fn send(&mut self, message: &Message) {
+        self.sent += 1;
+
+        if random::rng().gen::<f32>() < self.drop_rate {
+            self.dropped += 1;
+            return;
+        }
+        self.in_flight
+            .push((self.time + self.latency, message.to_wire()));
+    }
+
+    /// Advances the bus by a frame and returns the messages that arrived
+    pub fn tick(&mut self) -> Vec<Message> {
+        self.time += 1.0 / 60.0;
+
+        let time = self.time;
+        let (arrived, in_flight) = self
+            .in_flight
+            .drain(..)
+            .partition(|(arrival, _)| *arrival <= time);
+        self.in_flight = in_flight;
+
+        arrived
+            .iter()
+            .filter_map(|(_, bytes): &(f32, Vec<u8>)| Message::from_wire(bytes).ok())
+            .collect()
+    }
+
+    //getters
+    pub fn get_time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn get_sent(&self) -> u32 {
+        self.sent
+    }
+
+    pub fn get_dropped(&self) -> u32 {
+        self.dropped
+    }
+}
@@ -0,0 +1,132 @@
+use super::{Message, V2iConfig};
+use crate::car::{Direction, Lane};
+
+/// Movement through the intersection a vehicle holds a grant for
+#[derive(Debug, Clone, PartialEq, Copy)]
+struct Reservation {
+    car_id: u64,
+    direction: Direction,
+    lane: Lane,
+    /// Bus time of the last message from the vehicle
+    last_seen: f32,
+}
+
+/// Intersection side of the protocol. Only knows about the vehicles through their messages and
+/// grants a crossing when no conflicting movement holds one.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IntersectionManager {
+    reservations: Vec<Reservation>,
+}
+
+impl IntersectionManager {
+    pub fn new() -> Self {
+        IntersectionManager {
+            reservations: Vec::new(),
+        }
+    }
+
+    /// Handles the messages addressed to the manager and returns the replies
+    pub fn handle(&mut self, messages: &[Message], time: f32, config: &V2iConfig) -> Vec<Message> {
+        let mut replies = Vec::new();
+
+        for message in messages {
+            match *message {
+                Message::Request {
+                    car_id,
+                    direction,
+                    lane,
+                    ..
+                } => {
+                    if let Some(reservation) = self.find(car_id) {
+                        reservation.last_seen = time;
+                        replies.push(Message::Grant { car_id });
+                        continue;
+                    }
+
+                    if self
+                        .reservations
+                        .iter()
+                        .any(|other| conflicts((direction, lane), (other.direction, other.lane)))
+                    {
+                        replies.push(Message::Reject { car_id });
+                    } else {
+                        self.reservations.push(Reservation {
+                            car_id,
+                            direction,
+                            lane,
+                            last_seen: time,
+                        });
+                        replies.push(Message::Grant { car_id });
+                    }
+                }
+                Message::Heartbeat { car_id, .. } => match self.find(car_id) {
+                    Some(reservation) => reservation.last_seen = time,
+                    // The grant timed out, or its revoke got lost on the way
+                    None => replies.push(Message::Revoke { car_id }),
+                },
+                Message::Cancel { car_id } => {
+                    self.reservations
+                        .retain(|reservation| reservation.car_id != car_id);
+                }
+                Message::Grant { .. } | Message::Reject { .. } | Message::Revoke { .. } => {}
+            }
+        }
+
+        // Vehicles that went silent lost their cancel on the way, or can't be heard anymore and
+        // have to ask again
+        self.reservations.retain(|reservation| {
+            let expired = time - reservation.last_seen >= config.timeout;
+            if expired {
+                replies.push(Message::Revoke {
+                    car_id: reservation.car_id,
+                });
+            }
+            !expired
+        });
+
+        replies
+    }
+
+    fn find(&mut self, car_id: u64) -> Option<&mut Reservation> {
+        self.reservations
+            .iter_mut()
+            .find(|reservation| reservation.car_id == car_id)
+    }
+
+    //getters
+    pub fn get_granted(&self) -> Vec<u64> {
+        self.reservations
+            .iter()
+            .map(|reservation| reservation.car_id)
+            .collect()
+    }
+}
+
+/// Whether the paths of two movements, given as heading and lane, cross or merge
+pub fn conflicts(a: (Direction, Lane), b: (Direction, Lane)) -> bool {
+    let opposite = matches!(
+        (a.0, b.0),
+        (Direction::North, Direction::South)
+            | (Direction::South, Direction::North)
+            | (Direction::East, Direction::West)
+            | (Direction::West, Direction::East)
+    );
+
+    if a == b {
+        // Vehicles of the same movement follow each other
+        false
+    } else if a.0 == b.0 {
+        // Lanes of one approach run side by side, only a right turn cuts across the bike lane
+        matches!((a.1, b.1), (Lane::Right, Lane::Bike) | (Lane::Bike, Lane::Right))
+    } else if a.0.turn(a.1) == b.0.turn(b.1) {
+        // Movements from different approaches merging into the same exit
+        true
+    } else if a.1 == Lane::Right || b.1 == Lane::Right {
+        false
+    } else if opposite {
+        // Only the straight movements pass each other, opposite left turns swing into each other
+        !matches!(a.1, Lane::Middle | Lane::Bike) || !matches!(b.1, Lane::Middle | Lane::Bike)
+    } else {
+        true
+    }
+}
@@ -0,0 +1,211 @@
+pub mod bus;
+pub mod manager;
+
+use serde::{Deserialize, Serialize};
+
+use crate::car::{vehicles::VehicleType, Car, Direction, Lane};
+use crate::intersection::control::get_distance_to_stop_line;
+use crate::state::GlobalState;
+
+/// Messages exchanged between the vehicles and the intersection manager.
+///
+/// On the wire every message is a JSON object tagged with its `type`, for example
+/// `{"type":"grant","car_id":3}`.
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    /// Vehicle asks to cross the intersection
+    Request {
+        car_id: u64,
+        direction: Direction,
+        lane: Lane,
+        vehicle_type: VehicleType,
+    },
+    /// Manager allows the vehicle to enter the intersection
+    Grant { car_id: u64 },
+    /// Manager turns the request down, the vehicle asks again later
+    Reject { car_id: u64 },
+    /// Vehicle has left the intersection and gives its grant back
+    Cancel { car_id: u64 },
+    /// Manager took the grant back after not hearing from the vehicle, the vehicle asks again
+    Revoke { car_id: u64 },
+    /// Position of a vehicle holding a grant, keeps the grant alive
+    Heartbeat {
+        car_id: u64,
+        x: f32,
+        y: f32,
+        speed: f32,
+    },
+}
+
+impl Message {
+    pub fn to_wire(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("messages always serialize")
+    }
+
+    pub fn from_wire(bytes: &[u8]) -> Result<Message, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+}
+
+/// Where a vehicle is in the protocol
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+pub enum Permission {
+    None,
+    Requested,
+    Granted,
+    Rejected,
+    /// Grant given back after crossing
+    Released,
+}
+
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct V2iConfig {
+    /// Vehicles need a grant from the intersection manager to enter when enabled
+    pub enabled: bool,
+    /// Seconds a message takes to arrive
+    pub latency: f32,
+    /// Share of the messages that never arrive
+    pub drop_rate: f32,
+    /// Distance from the stop line in lane widths where vehicles ask to cross
+    pub request_distance: f32,
+    /// Seconds before a vehicle asks again after a reject or no answer
+    pub request_interval: f32,
+    /// Seconds between the heartbeats of a vehicle holding a grant
+    pub heartbeat_interval: f32,
+    /// Seconds without a message after which the manager takes a grant back
+    pub timeout: f32,
+}
+
+impl Default for V2iConfig {
+    fn default() -> Self {
+        V2iConfig {
+            enabled: false,
+            latency: 0.05,
+            drop_rate: 0.02,
+            request_distance: 4.0,
+            request_interval: 0.5,
+            heartbeat_interval: 0.25,
+            timeout: 2.0,
+        }
+    }
+}
+
+/// Emergency vehicles have their own priority and cyclists ride past the intersection, neither
+/// needs a grant.
+pub fn uses_v2i(car: &Car) -> bool {
+    !matches!(
+        car.get_vehicle_type(),
+        VehicleType::Emergency | VehicleType::Bicycle
+    )
+}
+
+/// Whether the intersection manager keeps the conflicting movements out of the car's way. Such
+/// a car crosses on its grant instead of yielding to the crossing traffic itself.
+pub fn holds_grant(car: &Car, global_state: &GlobalState) -> bool {
+    global_state.get_config().v2i.enabled
+        && uses_v2i(car)
+        && car.get_permission() == Permission::Granted
+}
+
+/// Delivers the messages on the bus, lets the manager answer and hands the answers to the cars.
+pub fn exchange_messages(global_state: &mut GlobalState) {
+    let config = global_state.get_config().v2i;
+    if !config.enabled {
+        return;
+    }
+
+    let mut bus = global_state.get_bus();
+    let mut manager = global_state.get_manager();
+    let messages = bus.tick();
+
+    for reply in manager.handle(&messages, bus.get_time(), &config) {
+        bus.send(&reply);
+    }
+
+    let mut cars = global_state.get_cars();
+    for car in cars.iter_mut() {
+        for message in messages.iter() {
+            match *message {
+                Message::Grant { car_id }
+                    if car_id == car.get_id() && car.get_permission() != Permission::Released =>
+                {
+                    car.set_permission(Permission::Granted)
+                }
+                Message::Reject { car_id }
+                    if car_id == car.get_id() && car.get_permission() == Permission::Requested =>
+                {
+                    car.set_permission(Permission::Rejected)
+                }
+                Message::Revoke { car_id }
+                    if car_id == car.get_id() && car.get_permission() == Permission::Granted =>
+                {
+                    car.set_permission(Permission::Rejected)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    global_state.set_bus(bus);
+    global_state.set_manager(manager);
+    global_state.set_cars(cars);
+}
+
+/// Sends the messages the car owes the manager this frame
+pub fn communicate(car: &mut Car, global_state: &mut GlobalState) {
+    let config = global_state.get_config().v2i;
+    if !config.enabled || !uses_v2i(car) {
+        return;
+    }
+
+    let dimensions = global_state.get_intersection().get_dimensions();
+    let mut bus = global_state.get_bus();
+    let car_id = car.get_id();
+    let request = Message::Request {
+        car_id,
+        direction: car.get_direction_from(),
+        lane: car.get_lane(),
+        vehicle_type: car.get_vehicle_type(),
+    };
+
+    car.set_message_time(car.get_message_time() + 1.0 / 60.0);
+
+    let message = match car.get_permission() {
+        Permission::None => {
+            let distance = get_distance_to_stop_line(car, dimensions);
+            if distance > config.request_distance * dimensions.get_lane_width() {
+                return;
+            }
+            car.set_permission(Permission::Requested);
+            request
+        }
+        Permission::Requested | Permission::Rejected => {
+            if car.get_message_time() < config.request_interval {
+                return;
+            }
+            request
+        }
+        Permission::Granted => {
+            if car.is_leaving_intersection() && !car.is_at_intersection(global_state) {
+                car.set_permission(Permission::Released);
+                Message::Cancel { car_id }
+            } else if car.get_message_time() >= config.heartbeat_interval {
+                Message::Heartbeat {
+                    car_id,
+                    x: car.get_x(),
+                    y: car.get_y(),
+                    speed: car.get_speed(),
+                }
+            } else {
+                return;
+            }
+        }
+        Permission::Released => return,
+    };
+
+    car.set_message_time(0.0);
+    bus.send(&message);
+    global_state.set_bus(bus);
+}
@@ -0,0 +1,163 @@
+//! Runs the controller server against stand-in controllers over a real socket.
+
+use smart_road::car::{Car, Direction};
+use smart_road::config::Config;
+use smart_road::controller::{Command, Commands, ControllerConfig, ControllerServer, WorldState};
+use smart_road::state::GlobalState;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn start(lock_step: bool, timeout: f32) -> (ControllerServer, TcpStream) {
+    let config = ControllerConfig {
+        enabled: true,
+        address: String::from("127.0.0.1:0"),
+        lock_step,
+        timeout,
+    };
+    let server = ControllerServer::start(&config).expect("server listens");
+    let stream = TcpStream::connect(server.get_address().expect("server has an address"))
+        .expect("client connects");
+    (server, stream)
+}
+
+fn world_with_cars(count: usize) -> GlobalState {
+    let mut global_state = GlobalState::headless(Config::default());
+    for direction in [Direction::North, Direction::East, Direction::South, Direction::West]
+        .into_iter()
+        .cycle()
+        .take(count)
+    {
+        let car = Car::new(direction, None, &global_state);
+        global_state.spawn_car(car);
+    }
+    global_state
+}
+
+#[test]
+fn lock_step_applies_the_answer_of_the_tick() {
+    let (mut server, stream) = start(true, 5.0);
+    let mut global_state = world_with_cars(2);
+
+    let client = thread::spawn(move || {
+        let mut writer = stream.try_clone().unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+        let state: WorldState = serde_json::from_str(&line).unwrap();
+
+        let commands = Commands {
+            commands: state
+                .cars
+                .iter()
+                .map(|car| Command {
+                    tick: state.tick,
+                    car_id: car.id,
+                    speed: Some(0.0),
+                    permission: Some(false),
+                })
+                .collect(),
+        };
+        writeln!(writer, "{}", serde_json::to_string(&commands).unwrap()).unwrap();
+        state
+    });
+
+    server.step(&mut global_state);
+    let state = client.join().unwrap();
+
+    assert_eq!(state.tick, 1);
+    assert_eq!(state.cars.len(), 2);
+    for car in global_state.get_cars() {
+        assert_eq!(car.get_commanded_speed(), Some(0.0));
+        assert_eq!(car.get_commanded_permission(), Some(false));
+    }
+}
+
+#[test]
+fn lock_step_drops_the_late_answer_of_an_earlier_tick() {
+    let (mut server, stream) = start(true, 5.0);
+    let mut global_state = world_with_cars(2);
+
+    let client = thread::spawn(move || {
+        let mut writer = stream.try_clone().unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+        let state: WorldState = serde_json::from_str(&line).unwrap();
+
+        for (tick, speed) in [(state.tick - 1, 0.0), (state.tick, 5.0)] {
+            let commands = Commands {
+                commands: state
+                    .cars
+                    .iter()
+                    .map(|car| Command {
+                        tick,
+                        car_id: car.id,
+                        speed: Some(speed),
+                        permission: None,
+                    })
+                    .collect(),
+            };
+            writeln!(writer, "{}", serde_json::to_string(&commands).unwrap()).unwrap();
+        }
+    });
+
+    server.step(&mut global_state);
+    client.join().unwrap();
+
+    for car in global_state.get_cars() {
+        assert_eq!(car.get_commanded_speed(), Some(5.0));
+    }
+}
+
+#[test]
+fn lock_step_falls_back_to_free_driving_when_the_controller_hangs() {
+    let (mut server, _stream) = start(true, 0.2);
+    let mut global_state = world_with_cars(2);
+    let commands: Vec<Command> = global_state
+        .get_cars()
+        .iter()
+        .map(|car| Command {
+            tick: 0,
+            car_id: car.get_id(),
+            speed: Some(0.0),
+            permission: Some(false),
+        })
+        .collect();
+    smart_road::controller::apply_commands(&commands, &mut global_state);
+
+    let start = Instant::now();
+    server.step(&mut global_state);
+
+    assert!(start.elapsed() < Duration::from_secs(2));
+    for car in global_state.get_cars() {
+        assert_eq!(car.get_commanded_speed(), None);
+        assert_eq!(car.get_commanded_permission(), None);
+    }
+}
+
+#[test]
+fn slow_controller_only_gets_whole_world_states() {
+    let (mut server, stream) = start(false, 1.0);
+    let mut global_state = world_with_cars(40);
+
+    // The controller doesn't read while the simulation fills the socket
+    for _ in 0..2000 {
+        server.step(&mut global_state);
+    }
+    drop(server);
+
+    // Every complete line is a whole state, only the one cut off by the shutdown isn't
+    let mut ticks = Vec::new();
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    while reader.read_line(&mut line).unwrap() > 0 && line.ends_with('\n') {
+        let state: WorldState = serde_json::from_str(&line).unwrap();
+        ticks.push(state.tick);
+        line.clear();
+    }
+
+    assert_eq!(ticks.first(), Some(&1));
+    assert!(ticks.windows(2).all(|pair| pair[0] < pair[1]));
+    // States that didn't fit into the socket were skipped
+    assert!(ticks.len() < 2000);
+}
@@ -0,0 +1,60 @@
+//! Runs seeded headless episodes and checks that no two vehicles ever overlap.
+
+use smart_road::config::Config;
+use smart_road::gym::{Action, Environment};
+
+fn assert_no_overlaps(seed: u64) {
+    let mut config = Config::default();
+    config.gym.frames_per_step = 1;
+    let mut environment = Environment::new(config);
+    environment.reset(seed);
+
+    loop {
+        let (_, _, done) = environment.step(&Action::Free);
+        let global_state = environment.get_global_state();
+        let cars = global_state.get_cars();
+
+        for (i, car) in cars.iter().enumerate() {
+            for other in cars.iter().skip(i + 1) {
+                assert!(
+                    !car.get_footprint().overlaps(&other.get_footprint()),
+                    "seed {seed}: {:?} #{} from {:?} in {:?} lane overlaps {:?} #{} from {:?} in \
+                     {:?} lane after {:.2} s",
+                    car.get_vehicle_type(),
+                    car.get_id(),
+                    car.get_direction_from(),
+                    car.get_lane(),
+                    other.get_vehicle_type(),
+                    other.get_id(),
+                    other.get_direction_from(),
+                    other.get_lane(),
+                    global_state.get_statistics().get_animation_time(),
+                );
+            }
+        }
+
+        if done {
+            break;
+        }
+    }
+}
+
+#[test]
+fn vehicles_never_overlap_seed_0() {
+    assert_no_overlaps(0);
+}
+
+#[test]
+fn vehicles_never_overlap_seed_1() {
+    assert_no_overlaps(1);
+}
+
+#[test]
+fn vehicles_never_overlap_seed_2() {
+    assert_no_overlaps(2);
+}
+
+#[test]
+fn vehicles_never_overlap_seed_3() {
+    assert_no_overlaps(3);
+}